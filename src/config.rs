@@ -7,8 +7,19 @@ use std::{
     fs::File,
     io::{BufReader, Read},
     path::{Path, PathBuf},
+    time::Duration,
 };
 
+use assetinfo::{
+    detection::{DetectionOptions, DEFAULT_CONCURRENCY},
+    extractor::{
+        allowlist::Allowlist,
+        environment::{ExecutionEnvironment, ResourceLimits, DEFAULT_PATH},
+        privilege::PrivilegeBackend,
+        ExtractorOptions,
+    },
+    providers::endoflife_date::DEFAULT_BASE_URL,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Serialize)]
@@ -26,10 +37,105 @@ pub struct DatabaseConfig {
     update_url: String,
 }
 
+#[derive(Deserialize, Serialize)]
+pub struct CacheConfig {
+    path: PathBuf,
+    /// Seconds until a cached endoflife.date response is requested again
+    #[serde(default = "CacheConfig::default_ttl")]
+    ttl: u64,
+}
+
+impl CacheConfig {
+    fn default_ttl() -> u64 {
+        24 * 60 * 60
+    }
+}
+
+#[derive(Deserialize, Serialize, Default)]
+pub struct HttpConfig {
+    /// Used for HTTP and HTTPS requests
+    proxy: Option<String>,
+    /// PEM file with additional root certificates
+    ca_bundle: Option<PathBuf>,
+    /// Seconds until a request is aborted
+    timeout: Option<u64>,
+    /// Retries of failed requests, with a backoff doubling from one second
+    #[serde(default)]
+    retries: u32,
+}
+
+#[derive(Deserialize, Serialize, Default)]
+pub struct ProvidersConfig {
+    /// Base URL of endoflife.date or a mirror of its API
+    endoflife_date_url: Option<String>,
+    /// TOML file with release cycles of products not on endoflife.date
+    file: Option<PathBuf>,
+    /// TOML file overriding release cycles of any provider
+    policy: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Serialize, Default)]
+pub struct DetectionConfig {
+    /// Extractors run at the same time
+    concurrency: Option<usize>,
+    /// Seconds until a binary is killed, unless its extractor sets a timeout
+    timeout: Option<u64>,
+    /// Bytes kept of the stdout and stderr of a binary each
+    max_output: Option<usize>,
+    /// Directories binaries given by name are looked for in after `PATH`
+    #[serde(default)]
+    search_paths: Vec<String>,
+    /// Also run the installations found by the `discover` patterns of the
+    /// database
+    #[serde(default)]
+    discover: bool,
+}
+
+#[derive(Deserialize, Serialize, Default)]
+pub struct ExecutionConfig {
+    /// `PATH` of the binaries run by extractors
+    path: Option<String>,
+    working_directory: Option<PathBuf>,
+    /// Run binaries through `setpriv --no-new-privs`
+    #[serde(default)]
+    no_new_privileges: bool,
+    /// Seconds of CPU time
+    cpu_limit: Option<u64>,
+    /// Bytes of virtual memory
+    memory_limit: Option<u64>,
+    open_files_limit: Option<u64>,
+    /// How binaries are run as the user of their extractor
+    #[serde(default)]
+    privilege_backend: PrivilegeBackend,
+    #[serde(default)]
+    allowlist: AllowlistConfig,
+}
+
+#[derive(Deserialize, Serialize, Default)]
+pub struct AllowlistConfig {
+    /// Directories binaries must be in
+    paths: Option<Vec<PathBuf>>,
+    /// Regexes every argument has to match one of
+    arguments: Option<Vec<String>>,
+    /// Users binaries may run as
+    users: Option<Vec<String>>,
+    #[serde(default)]
+    denied_users: Vec<String>,
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct Config {
     log_level: Option<LogLevel>,
     database: DatabaseConfig,
+    cache: Option<CacheConfig>,
+    #[serde(default)]
+    providers: ProvidersConfig,
+    #[serde(default)]
+    http: HttpConfig,
+    #[serde(default)]
+    detection: DetectionConfig,
+    #[serde(default)]
+    execution: ExecutionConfig,
 }
 
 impl Config {
@@ -53,6 +159,106 @@ impl Config {
         &self.database.update_url
     }
 
+    pub fn cache_folder(&self) -> Option<&Path> {
+        self.cache.as_ref().map(|cache| cache.path.as_path())
+    }
+
+    pub fn cache_ttl(&self) -> Duration {
+        Duration::from_secs(
+            self.cache
+                .as_ref()
+                .map_or_else(CacheConfig::default_ttl, |cache| cache.ttl),
+        )
+    }
+
+    pub fn endoflife_date_url(&self) -> &str {
+        self.providers
+            .endoflife_date_url
+            .as_deref()
+            .unwrap_or(DEFAULT_BASE_URL)
+    }
+
+    pub fn proxy(&self) -> Option<&str> {
+        self.http.proxy.as_deref()
+    }
+
+    pub fn ca_bundle(&self) -> Option<&Path> {
+        self.http.ca_bundle.as_deref()
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.http.timeout.map(Duration::from_secs)
+    }
+
+    pub fn retries(&self) -> u32 {
+        self.http.retries
+    }
+
+    pub fn lifecycle_file(&self) -> Option<&Path> {
+        self.providers.file.as_deref()
+    }
+
+    pub fn policy_file(&self) -> Option<&Path> {
+        self.providers.policy.as_deref()
+    }
+
+    pub fn detection_options(&self) -> Result<DetectionOptions, regex::Error> {
+        Ok(DetectionOptions {
+            concurrency: self.detection.concurrency.unwrap_or(DEFAULT_CONCURRENCY),
+            extractor: ExtractorOptions {
+                timeout: self
+                    .detection
+                    .timeout
+                    .map_or(ExtractorOptions::DEFAULT_TIMEOUT, Duration::from_secs),
+                max_output: self
+                    .detection
+                    .max_output
+                    .unwrap_or(ExtractorOptions::DEFAULT_MAX_OUTPUT),
+                environment: self.execution_environment(),
+                allowlist: self.allowlist()?,
+                search_paths: self.detection.search_paths.clone(),
+                discover: self.detection.discover,
+            },
+        })
+    }
+
+    fn allowlist(&self) -> Result<Allowlist, regex::Error> {
+        let allowlist = &self.execution.allowlist;
+
+        Ok(Allowlist {
+            paths: allowlist.paths.clone(),
+            arguments: allowlist
+                .arguments
+                .as_deref()
+                .map(Allowlist::argument_patterns)
+                .transpose()?,
+            users: allowlist.users.clone(),
+            denied_users: allowlist.denied_users.clone(),
+        })
+    }
+
+    fn execution_environment(&self) -> ExecutionEnvironment {
+        ExecutionEnvironment {
+            path: self
+                .execution
+                .path
+                .clone()
+                .unwrap_or_else(|| DEFAULT_PATH.to_string()),
+            working_directory: self
+                .execution
+                .working_directory
+                .clone()
+                .unwrap_or_else(|| PathBuf::from("/")),
+            no_new_privileges: self.execution.no_new_privileges,
+            limits: ResourceLimits {
+                cpu: self.execution.cpu_limit,
+                memory: self.execution.memory_limit,
+                open_files: self.execution.open_files_limit,
+            },
+            privilege_backend: self.execution.privilege_backend,
+        }
+    }
+
     pub fn log_level(&self) -> Option<log::Level> {
         match self.log_level {
             Some(LogLevel::Error) => Some(log::Level::Error),
@@ -3,17 +3,33 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use std::{
-    error::Error,
     fs::File,
+    io,
     path::{Path, PathBuf},
 };
 
 use async_compression::tokio::bufread::ZstdDecoder;
-use log::info;
+use log::{info, warn};
 use tar::Archive;
+use thiserror::Error;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
-use crate::program::Program;
+use crate::{http::HttpClient, program::Program};
+
+#[derive(Error, Debug)]
+pub enum DatabaseError {
+    #[error("IO Error: {0}")]
+    IoError(#[from] io::Error),
+
+    #[error("Download failed: {0}")]
+    DownloadError(#[from] reqwest::Error),
+
+    #[error("Download location {0:?} does not name a file")]
+    MissingFilename(String),
+
+    #[error("Invalid database update file '{}'", .0.display())]
+    InvalidUpdateFile(PathBuf),
+}
 
 pub struct Database {
     pub path: PathBuf,
@@ -21,31 +37,31 @@ pub struct Database {
 }
 
 impl Database {
-    pub fn load(path: &Path) -> Result<Database, Box<dyn Error>> {
+    pub fn load(path: &Path) -> Result<Database, DatabaseError> {
         let mut supported_programs: Vec<Program> = Vec::default();
 
-        for entry in path
-            .read_dir()
-            .expect("Could not read database directory")
-            .flatten()
-        {
+        for entry in path.read_dir()?.flatten() {
             if !entry.file_type()?.is_file() {
                 continue;
             }
 
-            if !str::ends_with(
-                entry
-                    .file_name()
-                    .to_str()
-                    .expect("Invalid filename in database"),
-                ".json",
-            ) {
+            if entry
+                .path()
+                .extension()
+                .is_none_or(|extension| extension != "json")
+            {
                 continue;
             }
 
             let file = File::open(entry.path())?;
-            let reader = std::io::BufReader::new(file);
-            supported_programs.push(serde_json::from_reader(reader)?);
+            let reader = io::BufReader::new(file);
+            match serde_json::from_reader(reader) {
+                Ok(program) => supported_programs.push(program),
+                Err(error) => warn!(
+                    "Skipping invalid database entry '{}': {error}",
+                    entry.path().display()
+                ),
+            }
         }
 
         Ok(Database {
@@ -70,31 +86,32 @@ impl Database {
     pub fn check_update() {}
 
     pub async fn download_update(
+        client: &HttpClient,
         download_location: &str,
         download_dir: &Path,
-    ) -> Result<PathBuf, Box<dyn Error>> {
+    ) -> Result<PathBuf, DatabaseError> {
         info!("Downloading new database '{download_location}'");
 
-        let response = reqwest::get(download_location).await?;
+        let response = client.get(download_location).await?;
 
         let filename = response
             .url()
             .path_segments()
             .and_then(Iterator::last)
             .and_then(|name| if name.is_empty() { None } else { Some(name) })
-            .unwrap();
+            .ok_or_else(|| DatabaseError::MissingFilename(download_location.to_string()))?;
 
         let filename = download_dir.join(filename);
-        info!("Saving new database at '{}'", filename.to_str().unwrap());
+        info!("Saving new database at '{}'", filename.display());
 
         let mut dest = File::create(filename.clone())?;
-        let bytes = response.bytes().await.unwrap();
+        let bytes = response.bytes().await?;
 
         info!("File size: {}", bytes.len());
 
         // let raw_bytes = bytes.to_vec();
 
-        let mut cursor = std::io::Cursor::new(bytes);
+        let mut cursor = io::Cursor::new(bytes);
 
         // let hash = sha256::digest(raw_bytes);
         // assert_eq!(
@@ -106,7 +123,7 @@ impl Database {
         //         .unwrap()
         // );
 
-        std::io::copy(&mut cursor, &mut dest)?;
+        io::copy(&mut cursor, &mut dest)?;
 
         Ok(filename)
     }
@@ -114,8 +131,11 @@ impl Database {
     pub async fn install_update(
         update_file: &Path,
         update_dir: &Path,
-    ) -> Result<(), Box<dyn Error>> {
-        let decompressed_file = update_dir.join(update_file.file_stem().unwrap());
+    ) -> Result<(), DatabaseError> {
+        let file_stem = update_file
+            .file_stem()
+            .ok_or_else(|| DatabaseError::InvalidUpdateFile(update_file.to_path_buf()))?;
+        let decompressed_file = update_dir.join(file_stem);
 
         Self::decompress_update(update_file, &decompressed_file).await?;
 
@@ -127,9 +147,9 @@ impl Database {
     async fn decompress_update(
         compressed_file: &Path,
         decompressed_file: &Path,
-    ) -> Result<(), Box<dyn Error>> {
-        let input = tokio::fs::File::open(compressed_file).await.unwrap();
-        let output = tokio::fs::File::create(decompressed_file).await.unwrap();
+    ) -> Result<(), DatabaseError> {
+        let input = tokio::fs::File::open(compressed_file).await?;
+        let output = tokio::fs::File::create(decompressed_file).await?;
 
         let mut reader = ZstdDecoder::new(tokio::io::BufReader::new(input));
         let mut data: Vec<u8> = vec![];
@@ -140,8 +160,8 @@ impl Database {
         Ok(output.write_all(&data).await?)
     }
 
-    fn extract_update(update_file: &Path, update_dir: &Path) -> Result<(), Box<dyn Error>> {
-        let mut ar = Archive::new(File::open(update_file).unwrap());
+    fn extract_update(update_file: &Path, update_dir: &Path) -> Result<(), DatabaseError> {
+        let mut ar = Archive::new(File::open(update_file)?);
 
         Ok(ar.unpack(update_dir)?)
     }
@@ -151,6 +171,7 @@ impl Database {
 mod tests {
 
     use crate::program::ProgramInfo;
+    use crate::version_scheme::VersionScheme;
     use std::fs::{self, File};
     use std::io::Write;
     use tempfile::TempDir;
@@ -168,7 +189,11 @@ mod tests {
                 id: "testprogram".to_string(),
                 title: "Testprogram".to_string(),
                 endoflife_date_id: None,
+                cycle_template: None,
+                cycle_mapping: None,
+                lifecycle: None,
             },
+            version_scheme: VersionScheme::default(),
             binary: None,
             docker: None,
         };
@@ -178,6 +203,14 @@ mod tests {
         tmp_file.flush().expect("Could not flush tmpfile");
         drop(tmp_file);
 
+        // Invalid entries are skipped instead of failing the whole database.
+        let invalid_path = tmp_dir.path().join("invalid.json");
+        fs::write(
+            &invalid_path,
+            r#"{"info": {"id": "invalid", "title": "Invalid"}, "binary": [{"path": "/bin/true", "arguments": [], "regex": "(?<cycle>\\d+)"}]}"#,
+        )
+        .expect("Could not write to tmpfile");
+
         let db = Database::load(tmp_dir.path());
 
         assert!(db.is_ok());
@@ -189,12 +222,21 @@ mod tests {
         fs::remove_file(file_path).expect("Could not delete tmpfile");
     }
 
+    #[test]
+    fn missing_directory() {
+        let tmp_dir = TempDir::new().expect("Could not create tmpdir");
+
+        let result = Database::load(&tmp_dir.path().join("missing"));
+
+        assert!(matches!(result, Err(DatabaseError::IoError(_))));
+    }
+
     #[tokio::test]
     #[ignore = "Needs network access (see integration tests)"]
     async fn download() {
         let tmp_dir = TempDir::new().expect("Could not create tmpdir");
 
-        let update_file = Database::download_update("https://db.assetinfo.de/d45ab56217ea96762255f6f8840c4625ed5a025760169038f5aa2454c109cd26.tar.zstd", tmp_dir.path()).await.expect("Download failed");
+        let update_file = Database::download_update(&HttpClient::default(), "https://db.assetinfo.de/d45ab56217ea96762255f6f8840c4625ed5a025760169038f5aa2454c109cd26.tar.zstd", tmp_dir.path()).await.expect("Download failed");
 
         Database::install_update(&update_file, tmp_dir.path())
             .await
@@ -0,0 +1,421 @@
+// SPDX-FileCopyrightText: 2024 Benedikt Bastin
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::{collections::HashSet, error::Error, path::PathBuf};
+
+use futures_util::{
+    future::{ready, LocalBoxFuture},
+    stream, FutureExt, Stream, StreamExt,
+};
+
+use crate::{
+    extractor::{
+        binary::BinaryExtractor, search::distinct, Extractor, ExtractorError, ExtractorOptions,
+    },
+    program::{Program, ProgramInfo, Version, VersionFormat},
+    providers::{Cycle, CycleResolver, ProviderError},
+};
+
+/// Number of extractors run at the same time unless configured otherwise.
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+#[derive(Clone, Debug)]
+pub struct DetectionOptions {
+    /// Extractors run at the same time
+    pub concurrency: usize,
+    pub extractor: ExtractorOptions,
+}
+
+impl Default for DetectionOptions {
+    fn default() -> Self {
+        DetectionOptions {
+            concurrency: DEFAULT_CONCURRENCY,
+            extractor: ExtractorOptions::default(),
+        }
+    }
+}
+
+/// The outcome of running one extractor of a program and looking up the
+/// release cycle of the version it found.
+#[derive(Debug)]
+pub enum Detection {
+    /// `release_cycle` is `None` if the program names no lifecycle product
+    /// or none of its cycles matches the version.
+    Found {
+        version: Version,
+        release_cycle: Option<Cycle>,
+    },
+    NotInstalled,
+    ExtractorError(ExtractorError),
+    ProviderError {
+        version: Version,
+        error: ProviderError,
+    },
+}
+
+impl Detection {
+    #[must_use]
+    pub fn is_error(&self) -> bool {
+        matches!(
+            self,
+            Detection::ExtractorError(_) | Detection::ProviderError { .. }
+        )
+    }
+
+    /// The error with all of its sources, e.g. for reports.
+    #[must_use]
+    pub fn error_text(&self) -> Option<String> {
+        match self {
+            Detection::ExtractorError(error) => Some(error_chain(error)),
+            Detection::ProviderError { error, .. } => Some(error_chain(error)),
+            Detection::Found { .. } | Detection::NotInstalled => None,
+        }
+    }
+}
+
+pub async fn detect<T: Extractor>(
+    program_info: &ProgramInfo,
+    extractor: &T,
+    format: &VersionFormat,
+    options: &ExtractorOptions,
+    resolver: &CycleResolver,
+) -> Detection {
+    match extractor.version(format, options).await {
+        Ok(Some(version)) => resolve_cycle(program_info, version, resolver).await,
+        Ok(None) => Detection::NotInstalled,
+        Err(error) => Detection::ExtractorError(error),
+    }
+}
+
+async fn resolve_cycle(
+    program_info: &ProgramInfo,
+    version: Version,
+    resolver: &CycleResolver,
+) -> Detection {
+    let Some(reference) = program_info.lifecycle_reference() else {
+        return Detection::Found {
+            version,
+            release_cycle: None,
+        };
+    };
+
+    match resolver
+        .resolve(&reference, &version, program_info.cycle_mapping.as_ref())
+        .await
+    {
+        Ok(release_cycle) => Detection::Found {
+            version,
+            release_cycle,
+        },
+        Err(error) => Detection::ProviderError { version, error },
+    }
+}
+
+/// The detection of one extractor of `program`.
+#[derive(Debug)]
+pub struct ProgramDetection<'a> {
+    pub program: &'a Program,
+    /// Name of the extractor, see [`Extractor::extractor_name`]
+    pub source: &'static str,
+    /// The installation of a binary extractor the version was read from
+    pub location: Option<PathBuf>,
+    pub detection: Detection,
+}
+
+/// Runs the extractors of all `programs`, at most `options.concurrency` at a
+/// time. Every distinct installation found by a binary extractor is detected
+/// on its own. The detections are returned in the order of the programs and
+/// their extractors, regardless of which finishes first.
+pub async fn detect_all<'a>(
+    programs: &'a [Program],
+    resolver: &'a CycleResolver,
+    options: &'a DetectionOptions,
+) -> Vec<ProgramDetection<'a>> {
+    detect_stream(programs, resolver, options).collect().await
+}
+
+/// Like [`detect_all`], but yields every detection as soon as it and all
+/// detections before it are done.
+pub fn detect_stream<'a>(
+    programs: &'a [Program],
+    resolver: &'a CycleResolver,
+    options: &'a DetectionOptions,
+) -> impl Stream<Item = ProgramDetection<'a>> + 'a {
+    let mut jobs: Vec<LocalBoxFuture<'a, ProgramDetection<'a>>> = Vec::default();
+
+    for program in programs {
+        // Extractors often list the same binary under several names. It is
+        // only run once by extractors that run it the same way.
+        let mut seen: Vec<(&BinaryExtractor, HashSet<PathBuf>)> = Vec::default();
+
+        for extractor in program.binary.iter().flatten() {
+            let installations = extractor.installations(&options.extractor);
+            if installations.is_empty() {
+                jobs.push(
+                    ready(ProgramDetection {
+                        program,
+                        source: BinaryExtractor::extractor_name(),
+                        location: None,
+                        detection: Detection::NotInstalled,
+                    })
+                    .boxed_local(),
+                );
+            }
+
+            let index = seen
+                .iter()
+                .position(|&(other, _)| other.runs_like(extractor))
+                .unwrap_or_else(|| {
+                    seen.push((extractor, HashSet::new()));
+                    seen.len() - 1
+                });
+
+            for path in distinct(installations, &mut seen[index].1) {
+                jobs.push(
+                    detect_installation(program, extractor, path, &options.extractor, resolver)
+                        .boxed_local(),
+                );
+            }
+        }
+
+        if let Some(ref extractor) = program.docker {
+            jobs.push(
+                detect_program(program, extractor, &options.extractor, resolver).boxed_local(),
+            );
+        }
+    }
+
+    stream::iter(jobs).buffered(options.concurrency.max(1))
+}
+
+async fn detect_program<'a, T: Extractor>(
+    program: &'a Program,
+    extractor: &'a T,
+    options: &'a ExtractorOptions,
+    resolver: &'a CycleResolver,
+) -> ProgramDetection<'a> {
+    let format = program.version_format();
+
+    ProgramDetection {
+        program,
+        source: T::extractor_name(),
+        location: None,
+        detection: detect(&program.info, extractor, &format, options, resolver).await,
+    }
+}
+
+async fn detect_installation<'a>(
+    program: &'a Program,
+    extractor: &'a BinaryExtractor,
+    path: PathBuf,
+    options: &'a ExtractorOptions,
+    resolver: &'a CycleResolver,
+) -> ProgramDetection<'a> {
+    let format = program.version_format();
+
+    let detection = match extractor.version_at(&path, &format, options).await {
+        Ok(version) => resolve_cycle(&program.info, version, resolver).await,
+        Err(error) => Detection::ExtractorError(error),
+    };
+
+    ProgramDetection {
+        program,
+        source: BinaryExtractor::extractor_name(),
+        location: Some(path),
+        detection,
+    }
+}
+
+/// Joins the messages of `error` and its sources, skipping sources whose
+/// message is already part of the previous one.
+#[must_use]
+pub fn error_chain(error: &dyn Error) -> String {
+    let mut text = error.to_string();
+    let mut source = error.source();
+
+    while let Some(cause) = source {
+        let cause_text = cause.to_string();
+        if !text.contains(&cause_text) {
+            text.push_str(": ");
+            text.push_str(&cause_text);
+        }
+        source = cause.source();
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs, io,
+        os::unix::fs::PermissionsExt,
+        path::{Path, PathBuf},
+    };
+
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::providers::composite::CompositeProvider;
+
+    fn program(id: &str, path: &Path) -> Program {
+        serde_json::from_value(serde_json::json!({
+            "info": {"id": id, "title": id, "endoflife_date_id": null},
+            "binary": [{
+                "path": path,
+                "user": null,
+                "arguments": [],
+                "regex": "^(?<version>(?<cycle>\\d+)\\.\\d+\\.\\d+)"
+            }],
+            "docker": null
+        }))
+        .expect("Invalid program")
+    }
+
+    fn write_script(tmp_dir: &TempDir, name: &str, content: &str) -> PathBuf {
+        let path = tmp_dir.path().join(name);
+        fs::write(&path, format!("#!/bin/sh\n{content}\n")).expect("Could not write script");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+            .expect("Could not set permission on script");
+        path
+    }
+
+    #[tokio::test]
+    async fn detections_keep_program_order() {
+        let tmp_dir = TempDir::new().expect("Could not create tmpdir");
+        let programs = vec![
+            program(
+                "slow",
+                &write_script(&tmp_dir, "slow", "sleep 0.2\necho 1.0.0"),
+            ),
+            program("missing", &tmp_dir.path().join("missing")),
+            program("fast", &write_script(&tmp_dir, "fast", "echo 2.0.0")),
+        ];
+        let resolver = CycleResolver::new(CompositeProvider::new(Vec::default()));
+
+        let options = DetectionOptions {
+            concurrency: 2,
+            ..DetectionOptions::default()
+        };
+
+        let detections = detect_all(&programs, &resolver, &options).await;
+
+        let summary: Vec<_> = detections
+            .iter()
+            .map(|program_detection| {
+                let version = match program_detection.detection {
+                    Detection::Found { ref version, .. } => Some(version.string.as_str()),
+                    _ => None,
+                };
+                (program_detection.program.info.id.as_str(), version)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("slow", Some("1.0.0")),
+                ("missing", None),
+                ("fast", Some("2.0.0"))
+            ]
+        );
+        assert!(detections
+            .iter()
+            .all(|program_detection| program_detection.source == "Binary"));
+    }
+
+    #[tokio::test]
+    async fn installations_are_detected_separately() {
+        let tmp_dir = TempDir::new().expect("Could not create tmpdir");
+        let old = write_script(&tmp_dir, "tool-1", "echo 1.0.0");
+        let new = write_script(&tmp_dir, "tool-2", "echo 2.0.0");
+        std::os::unix::fs::symlink(&new, tmp_dir.path().join("tool"))
+            .expect("Could not create symlink");
+
+        let mut program = program("tool", &tmp_dir.path().join("tool-*"));
+        let mut by_name = program.binary.as_ref().unwrap()[0].clone();
+        by_name.path = PathBuf::from("tool");
+        program.binary.as_mut().unwrap().push(by_name);
+
+        let resolver = CycleResolver::new(CompositeProvider::new(Vec::default()));
+        let mut options = DetectionOptions::default();
+        options.extractor.environment.path = tmp_dir.path().display().to_string();
+
+        let programs = [program];
+        let detections = detect_all(&programs, &resolver, &options).await;
+
+        let summary: Vec<_> = detections
+            .iter()
+            .map(|program_detection| {
+                let version = match program_detection.detection {
+                    Detection::Found { ref version, .. } => Some(version.string.as_str()),
+                    _ => None,
+                };
+                (program_detection.location.clone(), version)
+            })
+            .collect();
+        // `tool` is the same binary as `tool-2` and is not run again.
+        assert_eq!(
+            summary,
+            [(Some(old), Some("1.0.0")), (Some(new), Some("2.0.0"))]
+        );
+    }
+
+    #[tokio::test]
+    async fn differing_extractors_run_the_same_binary() {
+        let tmp_dir = TempDir::new().expect("Could not create tmpdir");
+        let tool = write_script(&tmp_dir, "tool", r#"echo "$1""#);
+
+        let mut program = program("tool", &tool);
+        let mut first = program.binary.as_ref().unwrap()[0].clone();
+        first.arguments = vec!["1.0.0".to_string()];
+        let mut second = first.clone();
+        second.arguments = vec!["2.0.0".to_string()];
+        let mut by_name = second.clone();
+        by_name.path = PathBuf::from("tool");
+        program.binary = Some(vec![first, second, by_name]);
+
+        let resolver = CycleResolver::new(CompositeProvider::new(Vec::default()));
+        let mut options = DetectionOptions::default();
+        options.extractor.environment.path = tmp_dir.path().display().to_string();
+
+        let programs = [program];
+        let detections = detect_all(&programs, &resolver, &options).await;
+
+        let versions: Vec<_> = detections
+            .iter()
+            .map(|program_detection| match program_detection.detection {
+                Detection::Found { ref version, .. } => version.string.as_str(),
+                _ => "",
+            })
+            .collect();
+        // Only the third extractor runs it like an earlier one.
+        assert_eq!(versions, ["1.0.0", "2.0.0"]);
+    }
+
+    #[test]
+    fn error_chains() {
+        let io_error = ExtractorError::IoError(io::Error::other("permission denied"));
+        assert_eq!(error_chain(&io_error), "IO Error: permission denied");
+
+        let detection = Detection::ExtractorError(io_error);
+        assert!(detection.is_error());
+        assert_eq!(
+            detection.error_text(),
+            Some("IO Error: permission denied".to_string())
+        );
+
+        let not_installed = Detection::NotInstalled;
+        assert!(!not_installed.is_error());
+        assert_eq!(not_installed.error_text(), None);
+
+        let provider_error = Detection::ProviderError {
+            version: "1.0.0".parse().unwrap(),
+            error: ProviderError::UnknownProduct("tool".to_string()),
+        };
+        assert_eq!(
+            provider_error.error_text(),
+            Some(r#"No lifecycle provider knows the product "tool""#.to_string())
+        );
+    }
+}
@@ -0,0 +1,203 @@
+// SPDX-FileCopyrightText: 2024 Benedikt Bastin
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::{
+    ffi::OsStr,
+    fmt::{self, Display},
+    path::PathBuf,
+    process::Command,
+};
+
+use crate::{
+    extractor::{Extractor, ExtractorOptions},
+    program::Program,
+    providers::CycleResolver,
+};
+
+/// Something detecting a program would do to the host or the network.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Action {
+    CheckFile(PathBuf),
+    /// Looking for installations, e.g. of a glob pattern
+    Search(String),
+    ReadFile(PathBuf),
+    WriteFile(PathBuf),
+    /// A command line, see [`command_line`]
+    Run(String),
+    DockerApi(String),
+    HttpRequest(String),
+    /// Why the remaining actions would not happen
+    Skip(String),
+}
+
+impl Display for Action {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::CheckFile(path) => write!(formatter, "check if {} exists", path.display()),
+            Action::Search(search) => write!(formatter, "search {search}"),
+            Action::ReadFile(path) => write!(formatter, "read {}", path.display()),
+            Action::WriteFile(path) => write!(formatter, "write {}", path.display()),
+            Action::Run(command) => write!(formatter, "run {command}"),
+            Action::DockerApi(call) => write!(formatter, "call Docker API {call}"),
+            Action::HttpRequest(request) => write!(formatter, "request {request}"),
+            Action::Skip(reason) => write!(formatter, "skip: {reason}"),
+        }
+    }
+}
+
+/// The actions of one extractor of `program`, or of the lifecycle lookup of
+/// `program` if `source` is [`LIFECYCLE_SOURCE`].
+#[derive(Debug)]
+pub struct Explanation<'a> {
+    pub program: &'a Program,
+    pub source: &'static str,
+    pub actions: Vec<Action>,
+}
+
+pub const LIFECYCLE_SOURCE: &str = "Lifecycle";
+
+/// Lists what detecting `programs` would do, without doing any of it.
+#[must_use]
+pub fn explain<'a>(
+    programs: &'a [Program],
+    resolver: &CycleResolver,
+    options: &ExtractorOptions,
+) -> Vec<Explanation<'a>> {
+    let mut explanations = Vec::default();
+
+    for program in programs {
+        for extractor in program.binary.iter().flatten() {
+            explanations.push(explain_extractor(program, extractor, options));
+        }
+
+        if let Some(ref extractor) = program.docker {
+            explanations.push(explain_extractor(program, extractor, options));
+        }
+
+        if let Some(reference) = program.info.lifecycle_reference() {
+            explanations.push(Explanation {
+                program,
+                source: LIFECYCLE_SOURCE,
+                actions: resolver.explain(&reference),
+            });
+        }
+    }
+
+    explanations
+}
+
+fn explain_extractor<'a, T: Extractor>(
+    program: &'a Program,
+    extractor: &T,
+    options: &ExtractorOptions,
+) -> Explanation<'a> {
+    Explanation {
+        program,
+        source: T::extractor_name(),
+        actions: extractor.explain(options),
+    }
+}
+
+/// `command` as it could be typed into a shell.
+#[must_use]
+pub fn command_line(command: &Command) -> String {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(quote)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn quote(argument: &OsStr) -> String {
+    let argument = argument.to_string_lossy();
+    let plain = !argument.is_empty()
+        && argument
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric() || "-_./=:,+@%".contains(character));
+
+    if plain {
+        argument.into_owned()
+    } else {
+        format!("'{}'", argument.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        http::HttpClient,
+        providers::{
+            composite::CompositeProvider,
+            endoflife_date::{cache::ResponseCache, EndOfLifeDateClient},
+        },
+    };
+
+    #[test]
+    fn explained_programs() {
+        let program: Program = serde_json::from_value(serde_json::json!({
+            "info": {"id": "org.postgresql", "title": "PostgreSQL", "endoflife_date_id": "postgresql"},
+            "binary": [{
+                "path": "/usr/bin/psql",
+                "user": "postgres",
+                "arguments": ["-c", "SELECT version();"],
+                "regex": "(?<version>\\d+)"
+            }],
+            "docker": null
+        }))
+        .expect("Invalid program");
+        let client = EndOfLifeDateClient::new("https://eol.example.com/api", HttpClient::default())
+            .with_cache(ResponseCache::new(
+                PathBuf::from("/var/cache/assetinfo"),
+                Duration::ZERO,
+            ));
+        let resolver = CycleResolver::new(CompositeProvider::new(vec![Box::new(client)]));
+
+        let programs = [program];
+        let explanations = explain(&programs, &resolver, &ExtractorOptions::default());
+
+        let actions: Vec<_> = explanations
+            .iter()
+            .map(|explanation| (explanation.source, explanation.actions.clone()))
+            .collect();
+        assert_eq!(
+            actions,
+            [
+                (
+                    "Binary",
+                    vec![
+                        Action::CheckFile(PathBuf::from("/usr/bin/psql")),
+                        Action::Run(
+                            "/usr/bin/sudo --non-interactive --user postgres -- /usr/bin/psql -c 'SELECT version();'"
+                                .to_string()
+                        )
+                    ]
+                ),
+                (
+                    LIFECYCLE_SOURCE,
+                    vec![
+                        Action::ReadFile(PathBuf::from("/var/cache/assetinfo/postgresql.json")),
+                        Action::HttpRequest(
+                            "GET https://eol.example.com/api/postgresql.json".to_string()
+                        ),
+                        Action::WriteFile(PathBuf::from("/var/cache/assetinfo/postgresql.json")),
+                    ]
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn command_lines() {
+        let mut command = Command::new("/usr/bin/psql");
+        command.args(["-c", "SELECT version();", "--no-psqlrc", "it's"]);
+
+        assert_eq!(
+            command_line(&command),
+            r"/usr/bin/psql -c 'SELECT version();' --no-psqlrc 'it'\''s'"
+        );
+    }
+}
@@ -2,17 +2,79 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use std::{path::PathBuf, time::Duration};
+
 use thiserror::Error;
 
-use crate::program::Version;
+use self::{allowlist::Allowlist, environment::ExecutionEnvironment, search::SearchPaths};
+use crate::{
+    explain::Action,
+    program::{Version, VersionFormat},
+    version_scheme::VersionError,
+};
 
+pub mod allowlist;
 pub mod binary;
 pub mod docker;
+pub mod environment;
+pub mod passwd;
+pub mod privilege;
 pub mod regex;
+pub mod search;
+
+/// Limits for running extractors, shared by all programs.
+#[derive(Clone, Debug)]
+pub struct ExtractorOptions {
+    /// Used unless the extractor defines its own timeout
+    pub timeout: Duration,
+    /// Bytes kept of stdout and stderr each, the rest is discarded
+    pub max_output: usize,
+    pub environment: ExecutionEnvironment,
+    pub allowlist: Allowlist,
+    /// Directories binaries given by name are looked for in after `PATH`,
+    /// may contain glob patterns
+    pub search_paths: Vec<String>,
+    /// Also run installations matching the `discover` patterns of binary
+    /// extractors
+    pub discover: bool,
+}
+
+impl ExtractorOptions {
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+    pub const DEFAULT_MAX_OUTPUT: usize = 64 * 1024;
+
+    #[must_use]
+    pub fn search_paths(&self) -> SearchPaths<'_> {
+        SearchPaths {
+            path: &self.environment.path,
+            extra: &self.search_paths,
+        }
+    }
+}
+
+impl Default for ExtractorOptions {
+    fn default() -> Self {
+        ExtractorOptions {
+            timeout: Self::DEFAULT_TIMEOUT,
+            max_output: Self::DEFAULT_MAX_OUTPUT,
+            environment: ExecutionEnvironment::default(),
+            allowlist: Allowlist::default(),
+            search_paths: Vec::default(),
+            discover: false,
+        }
+    }
+}
 
 pub trait Extractor {
     #[allow(async_fn_in_trait)]
-    async fn version(&self) -> Result<Option<Version>, ExtractorError>;
+    async fn version(
+        &self,
+        format: &VersionFormat,
+        options: &ExtractorOptions,
+    ) -> Result<Option<Version>, ExtractorError>;
+
+    /// What [`Extractor::version`] would do, without doing it.
+    fn explain(&self, options: &ExtractorOptions) -> Vec<Action>;
 
     fn extractor_name() -> &'static str;
 }
@@ -25,12 +87,36 @@ pub enum ExtractorError {
     #[error("Could not construct regex. Reason: {0}")]
     RegexError(#[from] ::regex::Error),
 
+    #[error("Regex is missing the required capture group {0:?}")]
+    MissingCaptureGroup(&'static str),
+
+    #[error("Capture group {0:?} did not participate in the match")]
+    UnmatchedCaptureGroup(&'static str),
+
     #[error("Could not parse Version number: {0}")]
     ParseIntError(#[from] std::num::ParseIntError),
 
+    #[error("Could not parse Version: {0}")]
+    SchemeError(#[from] VersionError),
+
     #[error("IO Error: {0}")]
     IoError(#[from] std::io::Error),
 
+    #[error("Timed out after {} seconds", .0.as_secs_f32())]
+    TimedOut(Duration),
+
+    #[error("Permission required to run as user {0:?}")]
+    PermissionRequired(String),
+
+    #[error("Unknown user {0:?}")]
+    UnknownUser(String),
+
+    #[error("Not allowed by the execution allowlist: {0}")]
+    NotAllowed(String),
+
+    #[error("Not running {}, which is not owned by root or us or is writable by others", .0.display())]
+    NotTrusted(PathBuf),
+
     #[error("Error on Docker Connection: {0}")]
     DockerError(#[from] bollard::errors::Error),
 }
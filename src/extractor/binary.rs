@@ -2,57 +2,155 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use core::str;
 use std::{
-    path::PathBuf,
-    process::{Command, Output},
+    collections::HashSet,
+    io,
+    os::unix::process::CommandExt,
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+    time::Duration,
 };
 
-use log::info;
+use log::{info, warn};
+use rustix::process::{kill_process_group, Pid, Signal};
 use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt},
+    time::timeout,
+};
+
+use crate::{
+    explain::{command_line, Action},
+    program::{Version, VersionFormat},
+};
 
-use crate::program::Version;
+use super::{
+    environment::ExecutionEnvironment,
+    regex::{parse_first_version, VersionRegexes},
+    search::{discover, distinct, is_trusted, BinaryLocation},
+    Extractor, ExtractorError, ExtractorOptions,
+};
 
-use super::{regex::parse_version, Extractor, ExtractorError};
+/// The output stream(s) of a binary that are searched for the version.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    /// stdout, or stderr if stdout is empty
+    #[default]
+    Auto,
+    Stdout,
+    Stderr,
+    /// stdout followed by stderr, which starts on a new line
+    Both,
+}
+
+impl OutputStream {
+    fn select(self, output: &Output) -> String {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        match self {
+            OutputStream::Auto if stdout.is_empty() => stderr.into_owned(),
+            OutputStream::Auto | OutputStream::Stdout => stdout.into_owned(),
+            OutputStream::Stderr => stderr.into_owned(),
+            OutputStream::Both if stdout.is_empty() || stdout.ends_with('\n') => {
+                format!("{stdout}{stderr}")
+            }
+            OutputStream::Both => format!("{stdout}\n{stderr}"),
+        }
+    }
+}
 
 #[derive(Deserialize, Serialize, Clone, PartialEq, Eq, Debug)]
 pub struct BinaryExtractor {
+    /// An absolute path, a glob pattern or a name looked up on `PATH`, see
+    /// [`BinaryLocation`]
     pub path: PathBuf,
     pub user: Option<String>,
     pub arguments: Vec<String>,
-    pub regex: String,
+    #[serde(default)]
+    pub output: OutputStream,
+    pub regex: VersionRegexes,
+    /// Seconds until the binary is killed, overrides the global timeout
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// Glob patterns of further installations, e.g. of version managers,
+    /// which are only searched in discovery mode. A leading `~` stands for
+    /// the home directory of every user in `/etc/passwd`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub discover: Vec<String>,
 }
 
 impl Extractor for BinaryExtractor {
-    async fn version(&self) -> Result<Option<Version>, ExtractorError> {
-        if !self.path.exists() {
+    /// The version of the first installation, see
+    /// [`BinaryExtractor::installations`].
+    async fn version(
+        &self,
+        format: &VersionFormat,
+        options: &ExtractorOptions,
+    ) -> Result<Option<Version>, ExtractorError> {
+        let Some(path) = self.installations(options).into_iter().next() else {
             return Ok(None);
-        }
-
-        let output = if self.user.is_some() {
-            self.run_as_other_user_sudo()?
-        } else {
-            self.run_as_user()?
         };
 
-        info!("Command executed");
+        self.version_at(&path, format, options).await.map(Some)
+    }
 
-        let fd = if output.stdout.is_empty() {
-            &output.stderr
-        } else {
-            &output.stdout
+    fn explain(&self, options: &ExtractorOptions) -> Vec<Action> {
+        if let Some(user) = self.skipped_user(options) {
+            return vec![Action::Skip(format!(
+                "Not running as root, which is required to run as {user:?}"
+            ))];
+        }
+
+        let location = BinaryLocation::new(&self.path);
+        let mut actions = Vec::default();
+
+        let mut installations = match location {
+            BinaryLocation::Path(path) => {
+                actions.push(Action::CheckFile(path.to_path_buf()));
+                vec![path.to_path_buf()]
+            }
+            BinaryLocation::Pattern(pattern) => {
+                actions.push(Action::Search(pattern.to_string()));
+                options.search_paths().installations(location)
+            }
+            BinaryLocation::Name(name) => {
+                let search_paths = options.search_paths();
+                let mut directories = search_paths.path.to_string();
+                for extra in search_paths.extra {
+                    directories.push(':');
+                    directories.push_str(extra);
+                }
+                actions.push(Action::Search(format!("{name} in {directories}")));
+                search_paths.installations(location)
+            }
         };
 
-        let string = str::from_utf8(fd);
+        if options.discover {
+            for pattern in &self.discover {
+                actions.push(Action::Search(pattern.clone()));
+                installations.extend(discover(pattern));
+            }
+            installations = distinct(installations, &mut HashSet::new());
+        }
 
-        if !output.status.success() {
-            return Err(ExtractorError::IoError(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                string.unwrap(),
-            )));
+        if installations.is_empty() {
+            actions.push(Action::Skip("No installation found".to_string()));
         }
 
-        Ok(Some(parse_version(string.unwrap(), &self.regex)?))
+        for path in installations {
+            let command = self
+                .check_allowlist(&path, options)
+                .and_then(|()| self.check_trusted(&path, options))
+                .and_then(|()| self.command(&path, &options.environment));
+            match command {
+                Ok(command) => actions.push(Action::Run(command_line(&command))),
+                Err(error) => actions.push(Action::Skip(error.to_string())),
+            }
+        }
+
+        actions
     }
 
     fn extractor_name() -> &'static str {
@@ -61,52 +159,214 @@ impl Extractor for BinaryExtractor {
 }
 
 impl BinaryExtractor {
-    fn run_as_user(&self) -> std::io::Result<Output> {
-        Command::new(self.path.clone())
-            .args(self.arguments.clone())
-            .output()
+    /// The paths of every distinct installation of the binary, see
+    /// [`BinaryLocation`]. In discovery mode, this includes the installations
+    /// matching the `discover` patterns, which are only run if they are
+    /// trusted, see [`is_trusted`].
+    /// Binaries that would run as a user the privilege backend does not
+    /// switch to have no installations.
+    #[must_use]
+    pub fn installations(&self, options: &ExtractorOptions) -> Vec<PathBuf> {
+        if let Some(user) = self.skipped_user(options) {
+            info!("Not running {} as {user:?}", self.path.display());
+            return Vec::default();
+        }
+
+        let installations = options
+            .search_paths()
+            .installations(BinaryLocation::new(&self.path));
+
+        if !options.discover {
+            return installations;
+        }
+
+        let discovered = self.discover.iter().flat_map(|pattern| discover(pattern));
+        distinct(
+            installations.into_iter().chain(discovered),
+            &mut HashSet::new(),
+        )
     }
 
-    #[allow(dead_code)]
-    fn run_as_other_user_systemd(&self) -> std::io::Result<Output> {
-        let user = self.user.clone().unwrap();
+    /// Runs the installation at `path`, which is usually one of
+    /// [`BinaryExtractor::installations`].
+    pub async fn version_at(
+        &self,
+        path: &Path,
+        format: &VersionFormat,
+        options: &ExtractorOptions,
+    ) -> Result<Version, ExtractorError> {
+        self.check_allowlist(path, options)?;
+        self.check_trusted(path, options)?;
+
+        let environment = &options.environment;
+        let command = self.command(path, environment)?;
+
+        info!("Running {}", command_line(&command));
+
+        let timeout = self.timeout.map_or(options.timeout, Duration::from_secs);
+        let output = execute(command, timeout, options.max_output).await?;
 
-        let mut args: Vec<String> = vec![
-            "--pty".to_string(),
-            //"--wait".to_string(),
-            //"--collect".to_string(),
-            //"--service-type=exec".to_string(),
-            "--quiet".to_string(),
-            format!("--uid={user}",),
-            self.path.to_str().unwrap().to_string(),
-        ];
+        info!("Command executed");
+
+        let string = self.output.select(&output);
 
-        for argument in self.arguments.clone() {
-            args.push(argument);
+        if !output.status.success() {
+            if let Some(ref user) = self.user {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                if environment.privilege_backend.permission_denied(&stderr) {
+                    return Err(ExtractorError::PermissionRequired(user.clone()));
+                }
+            }
+            return Err(ExtractorError::IoError(io::Error::other(string)));
         }
 
-        info!("Running /usr/bin/systemd-run {args:?}",);
+        parse_first_version(&string, &self.regex, format)
+    }
 
-        Command::new("/usr/bin/systemd-run").args(args).output()
+    /// Whether `other` runs binaries the same way and only differs in where
+    /// it finds them.
+    #[must_use]
+    pub fn runs_like(&self, other: &BinaryExtractor) -> bool {
+        let BinaryExtractor {
+            path: _,
+            user,
+            arguments,
+            output,
+            regex,
+            timeout,
+            discover: _,
+        } = self;
+
+        *user == other.user
+            && *arguments == other.arguments
+            && *output == other.output
+            && *regex == other.regex
+            && *timeout == other.timeout
     }
 
-    fn run_as_other_user_sudo(&self) -> std::io::Result<Output> {
-        let user = self.user.clone().unwrap();
+    /// The user of the extractor, if the privilege backend does not run
+    /// binaries as it.
+    fn skipped_user(&self, options: &ExtractorOptions) -> Option<&str> {
+        self.user
+            .as_deref()
+            .filter(|_| !options.environment.privilege_backend.switches_users())
+    }
 
-        let mut args: Vec<String> = vec![
-            "-u".to_string(),
-            user,
-            self.path.to_str().unwrap().to_string(),
-        ];
+    fn check_allowlist(
+        &self,
+        path: &Path,
+        options: &ExtractorOptions,
+    ) -> Result<(), ExtractorError> {
+        options
+            .allowlist
+            .check(path, &self.arguments, self.user.as_deref())
+    }
 
-        for argument in self.arguments.clone() {
-            args.push(argument);
+    /// Discovered installations may belong to other users, who could make us
+    /// run anything. They are reported instead of being run or ignored.
+    fn check_trusted(&self, path: &Path, options: &ExtractorOptions) -> Result<(), ExtractorError> {
+        if !options.discover || is_trusted(path) {
+            return Ok(());
         }
 
-        info!("Running /usr/bin/sudo {args:?}",);
+        let regular = options
+            .search_paths()
+            .installations(BinaryLocation::new(&self.path));
+        if regular.iter().any(|installation| installation == path) {
+            Ok(())
+        } else {
+            Err(ExtractorError::NotTrusted(path.to_path_buf()))
+        }
+    }
+
+    /// The command running the binary at `path` in `environment`, as the
+    /// extractor's user if it has one.
+    fn command(
+        &self,
+        path: &Path,
+        environment: &ExecutionEnvironment,
+    ) -> Result<Command, ExtractorError> {
+        let (program, args) = environment.wrap(path, &self.arguments);
+
+        let mut command = if let Some(ref user) = self.user {
+            environment
+                .privilege_backend
+                .command(user, &program, &args)?
+        } else {
+            let mut command = Command::new(program);
+            command.args(args);
+            command
+        };
+        environment.apply(&mut command);
+
+        Ok(command)
+    }
+}
+
+/// Runs `command` in its own process group without stdin. The whole group is
+/// killed if it does not exit within `limit`, e.g. because it waits for a
+/// password prompt.
+async fn execute(
+    mut command: Command,
+    limit: Duration,
+    max_output: usize,
+) -> Result<Output, ExtractorError> {
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0);
+
+    let mut child = tokio::process::Command::from(command)
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let read_stdout = read_limited(child.stdout.take(), max_output);
+    let read_stderr = read_limited(child.stderr.take(), max_output);
+
+    let result = timeout(limit, async {
+        tokio::try_join!(read_stdout, read_stderr, child.wait())
+    })
+    .await;
+
+    let Ok(result) = result else {
+        let process_group = child
+            .id()
+            .and_then(|id| i32::try_from(id).ok())
+            .and_then(Pid::from_raw);
+        if let Some(process_group) = process_group {
+            if let Err(error) = kill_process_group(process_group, Signal::Kill) {
+                warn!("Could not kill process group {process_group:?}: {error}");
+            }
+        }
+        return Err(ExtractorError::TimedOut(limit));
+    };
+
+    let (stdout, stderr, status) = result?;
+    Ok(Output {
+        status,
+        stdout,
+        stderr,
+    })
+}
 
-        Command::new("/usr/bin/sudo").args(args).output()
+/// Keeps the first `limit` bytes and discards the rest, so the child does not
+/// block on a full pipe.
+async fn read_limited<T: AsyncRead + Unpin>(
+    reader: Option<T>,
+    limit: usize,
+) -> io::Result<Vec<u8>> {
+    let mut buffer = Vec::default();
+
+    if let Some(mut reader) = reader {
+        (&mut reader)
+            .take(u64::try_from(limit).unwrap_or(u64::MAX))
+            .read_to_end(&mut buffer)
+            .await?;
+        tokio::io::copy(&mut reader, &mut tokio::io::sink()).await?;
     }
+
+    Ok(buffer)
 }
 
 #[cfg(test)]
@@ -114,9 +374,22 @@ mod tests {
 
     use std::fs::{self, File};
     use std::io::Write;
+    use std::os::unix::fs::PermissionsExt;
     use tempfile::TempDir;
 
     use super::*;
+    use crate::extractor::regex::VersionRegex;
+
+    fn write_script(tmp_dir: &TempDir, content: &str) -> PathBuf {
+        let file_path = tmp_dir.path().join("testprogram");
+        fs::write(&file_path, format!("#!/bin/sh\n{content}\n")).expect("Could not write tmpfile");
+        Command::new("/bin/chmod")
+            .arg("+x")
+            .arg(file_path.clone())
+            .output()
+            .expect("Could not set permission on tmpfile");
+        file_path
+    }
 
     #[tokio::test]
     async fn test() {
@@ -136,11 +409,19 @@ mod tests {
             path: file_path.clone(),
             user: None,
             arguments: Vec::default(),
-            regex: "^(?<version>(?<cycle>(?<major>\\d+))\\.(?<minor>\\d+)\\.(?<patch>\\d+))"
-                .to_string(),
+            output: OutputStream::default(),
+            timeout: None,
+            discover: Vec::default(),
+            regex: VersionRegex::new(
+                "^(?<version>(?<cycle>(?<major>\\d+))\\.(?<minor>\\d+)\\.(?<patch>\\d+))",
+            )
+            .unwrap()
+            .into(),
         };
 
-        let res = extractor.version().await;
+        let res = extractor
+            .version(&VersionFormat::default(), &ExtractorOptions::default())
+            .await;
         if let Err(error) = res {
             panic!("{error}");
         }
@@ -154,4 +435,230 @@ mod tests {
 
         fs::remove_file(file_path).expect("Could not delete tmpfile");
     }
+
+    #[tokio::test]
+    async fn output_streams() {
+        let tmp_dir = TempDir::new().expect("Could not create tmpdir");
+        let file_path = write_script(
+            &tmp_dir,
+            "echo 'Picked up JAVA_TOOL_OPTIONS: -Xmx1g'\necho 'openjdk version \"21.0.4\" 2024-07-16' >&2",
+        );
+
+        let mut extractor = BinaryExtractor {
+            path: file_path,
+            user: None,
+            arguments: Vec::default(),
+            output: OutputStream::Stderr,
+            timeout: None,
+            discover: Vec::default(),
+            regex: VersionRegexes(vec![
+                VersionRegex::new(r#"^java version "(?<version>(?<cycle>\d+)\.\d+\.\d+)""#)
+                    .unwrap(),
+                VersionRegex::new(r#"(?m)^openjdk version "(?<version>(?<cycle>\d+)\.\d+\.\d+)""#)
+                    .unwrap(),
+            ]),
+        };
+
+        let version = extractor
+            .version(&VersionFormat::default(), &ExtractorOptions::default())
+            .await
+            .expect("Could not extract version")
+            .expect("Binary not found");
+        assert_eq!(version.string, "21.0.4");
+
+        extractor.output = OutputStream::Both;
+        assert!(extractor
+            .version(&VersionFormat::default(), &ExtractorOptions::default())
+            .await
+            .is_ok());
+
+        extractor.output = OutputStream::Stdout;
+        assert!(extractor
+            .version(&VersionFormat::default(), &ExtractorOptions::default())
+            .await
+            .is_err());
+
+        extractor.output = OutputStream::Auto;
+        assert!(extractor
+            .version(&VersionFormat::default(), &ExtractorOptions::default())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn both_streams_without_trailing_newline() {
+        let tmp_dir = TempDir::new().expect("Could not create tmpdir");
+        let file_path = write_script(
+            &tmp_dir,
+            "printf 'Picked up JAVA_TOOL_OPTIONS: -Xmx1g'\necho 'openjdk version \"21.0.4\"' >&2",
+        );
+
+        let extractor = BinaryExtractor {
+            path: file_path,
+            user: None,
+            arguments: Vec::default(),
+            output: OutputStream::Both,
+            timeout: None,
+            discover: Vec::default(),
+            regex: VersionRegex::new(
+                r#"(?m)^openjdk version "(?<version>(?<cycle>\d+)\.\d+\.\d+)""#,
+            )
+            .unwrap()
+            .into(),
+        };
+
+        let version = extractor
+            .version(&VersionFormat::default(), &ExtractorOptions::default())
+            .await
+            .expect("Could not extract version")
+            .expect("Binary not found");
+        assert_eq!(version.string, "21.0.4");
+    }
+
+    #[tokio::test]
+    async fn timeouts_and_output_limits() {
+        let tmp_dir = TempDir::new().expect("Could not create tmpdir");
+        let file_path = write_script(&tmp_dir, "echo 1.2.3\nhead -c 1000000 /dev/zero\nsleep 5");
+
+        let mut extractor = BinaryExtractor {
+            path: file_path,
+            user: None,
+            arguments: Vec::default(),
+            output: OutputStream::Stdout,
+            timeout: None,
+            discover: Vec::default(),
+            regex: VersionRegex::new("^(?<version>(?<cycle>\\d+)\\.\\d+\\.\\d+)")
+                .unwrap()
+                .into(),
+        };
+        let options = ExtractorOptions {
+            timeout: Duration::from_millis(500),
+            max_output: 16,
+            ..ExtractorOptions::default()
+        };
+
+        let started = std::time::Instant::now();
+        let result = extractor.version(&VersionFormat::default(), &options).await;
+        assert!(matches!(result, Err(ExtractorError::TimedOut(_))));
+        assert!(started.elapsed() < Duration::from_secs(4));
+
+        // The extractor's own timeout takes precedence.
+        extractor.timeout = Some(0);
+        assert!(matches!(
+            extractor
+                .version(&VersionFormat::default(), &ExtractorOptions::default())
+                .await,
+            Err(ExtractorError::TimedOut(limit)) if limit.is_zero()
+        ));
+
+        let mut command = Command::new("/bin/sh");
+        command
+            .arg("-c")
+            .arg("echo 1.2.3; head -c 1000000 /dev/zero");
+        let output = execute(command, Duration::from_secs(5), 16)
+            .await
+            .expect("Could not run command");
+        assert_eq!(output.stdout.len(), 16);
+        assert!(output.stdout.starts_with(b"1.2.3"));
+    }
+
+    #[test]
+    fn discovered_installations() {
+        let tmp_dir = TempDir::new().expect("Could not create tmpdir");
+        for version in ["8", "17"] {
+            fs::create_dir_all(tmp_dir.path().join(format!("jvm/{version}/bin")))
+                .expect("Could not create tmpdir");
+        }
+        let java_8 = tmp_dir.path().join("jvm/8/bin/java");
+        let java_17 = tmp_dir.path().join("jvm/17/bin/java");
+        fs::rename(write_script(&tmp_dir, "echo 8"), &java_8).expect("Could not move script");
+        fs::rename(write_script(&tmp_dir, "echo 17"), &java_17).expect("Could not move script");
+
+        let extractor = BinaryExtractor {
+            path: java_17.clone(),
+            user: None,
+            arguments: Vec::default(),
+            output: OutputStream::default(),
+            timeout: None,
+            discover: vec![format!("{}/jvm/*/bin/java", tmp_dir.path().display())],
+            regex: VersionRegex::new("^(?<version>\\d+)").unwrap().into(),
+        };
+
+        let mut options = ExtractorOptions::default();
+        assert_eq!(
+            extractor.installations(&options),
+            std::slice::from_ref(&java_17)
+        );
+
+        options.discover = true;
+        assert_eq!(extractor.installations(&options), [java_17, java_8]);
+    }
+
+    #[tokio::test]
+    async fn untrusted_installations() {
+        let tmp_dir = TempDir::new().expect("Could not create tmpdir");
+        let file_path = write_script(&tmp_dir, "echo 1.0.0");
+        fs::set_permissions(tmp_dir.path(), fs::Permissions::from_mode(0o777))
+            .expect("Could not set permission on tmpdir");
+
+        let mut extractor = BinaryExtractor {
+            path: file_path.clone(),
+            user: None,
+            arguments: Vec::default(),
+            output: OutputStream::default(),
+            timeout: None,
+            discover: Vec::default(),
+            regex: VersionRegex::new("^(?<version>\\d+)").unwrap().into(),
+        };
+        let options = ExtractorOptions {
+            discover: true,
+            ..ExtractorOptions::default()
+        };
+
+        // Configured paths are run regardless of their owner.
+        assert!(extractor
+            .version_at(&file_path, &VersionFormat::default(), &options)
+            .await
+            .is_ok());
+
+        extractor.path = PathBuf::from("/nonexistent/testprogram");
+        extractor.discover = vec![format!("{}/*", tmp_dir.path().display())];
+        assert_eq!(
+            extractor.installations(&options),
+            std::slice::from_ref(&file_path)
+        );
+        assert!(matches!(
+            extractor
+                .version_at(&file_path, &VersionFormat::default(), &options)
+                .await,
+            Err(ExtractorError::NotTrusted(path)) if path == file_path
+        ));
+    }
+
+    #[tokio::test]
+    async fn cleared_environment() {
+        let tmp_dir = TempDir::new().expect("Could not create tmpdir");
+        let file_path = write_script(&tmp_dir, r#"echo "1.0.0 home=$HOME lang=$LANG pwd=$(pwd)""#);
+
+        let extractor = BinaryExtractor {
+            path: file_path,
+            user: None,
+            arguments: Vec::default(),
+            output: OutputStream::Stdout,
+            timeout: None,
+            discover: Vec::default(),
+            regex: VersionRegex::new(
+                r"(?m)^(?<version>(?<cycle>\d+)\.\d+\.\d+) home= lang=C pwd=/$",
+            )
+            .unwrap()
+            .into(),
+        };
+
+        let version = extractor
+            .version(&VersionFormat::default(), &ExtractorOptions::default())
+            .await
+            .expect("Could not extract version")
+            .expect("Binary not found");
+        assert_eq!(version.string, "1.0.0");
+    }
 }
@@ -4,11 +4,19 @@
 
 use bollard::{container::ListContainersOptions, secret::ContainerSummary, Docker};
 use serde::{Deserialize, Serialize};
-use std::{error::Error, path::PathBuf};
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+use tokio::time::timeout;
 
-use crate::{extractor::regex, program::Version};
+use crate::{
+    explain::Action,
+    extractor::regex::{self, VersionRegexes},
+    program::{Version, VersionFormat},
+};
 
-use super::{Extractor, ExtractorError};
+use super::{Extractor, ExtractorError, ExtractorOptions};
 
 pub struct Connection {
     connection: Docker,
@@ -26,6 +34,7 @@ impl Connection {
     pub async fn info(
         &self,
         extractor: &DockerExtractor,
+        format: &VersionFormat,
     ) -> Result<Option<Version>, ExtractorError> {
         let result = &self
             .connection
@@ -48,7 +57,8 @@ impl Connection {
                 continue;
             }
 
-            if let Ok(Some(version)) = Self::match_oci_version_label(res, &extractor.regex) {
+            if let Ok(Some(version)) = Self::match_oci_version_label(res, &extractor.regex, format)
+            {
                 return Ok(Some(version));
             }
         }
@@ -61,15 +71,16 @@ impl Connection {
 
     fn match_oci_version_label(
         container_summary: &ContainerSummary,
-        regex: &str,
-    ) -> Result<Option<Version>, Box<dyn Error>> {
+        regexes: &VersionRegexes,
+        format: &VersionFormat,
+    ) -> Result<Option<Version>, ExtractorError> {
         if let Some(labels) = container_summary.labels.clone() {
             const VERSION_LABEL: &str = "org.opencontainers.image.version";
 
             let label = labels.get(VERSION_LABEL);
 
             if let Some(str) = label {
-                let version = regex::parse_version(str, regex)?;
+                let version = regex::parse_first_version(str, regexes, format)?;
 
                 return Ok(Some(version));
             }
@@ -83,14 +94,52 @@ pub struct DockerExtractor {
     pub image_name: String,
     pub binary_path: Option<PathBuf>,
     pub arguments: Option<Vec<String>>,
-    pub regex: String,
+    pub regex: VersionRegexes,
+}
+
+/// Socket used by bollard unless `DOCKER_HOST` is set
+const DOCKER_SOCKET: &str = "/var/run/docker.sock";
+
+/// Whether bollard would find a Docker daemon to connect to.
+fn docker_available() -> bool {
+    env::var_os("DOCKER_HOST").is_some() || Path::new(DOCKER_SOCKET).exists()
 }
 
 impl Extractor for DockerExtractor {
-    async fn version(&self) -> Result<Option<Version>, ExtractorError> {
+    async fn version(
+        &self,
+        format: &VersionFormat,
+        options: &ExtractorOptions,
+    ) -> Result<Option<Version>, ExtractorError> {
+        if !docker_available() {
+            return Ok(None);
+        }
+
         let connection = Connection::connect()?;
 
-        connection.info(self).await
+        timeout(options.timeout, connection.info(self, format))
+            .await
+            .map_err(|_| ExtractorError::TimedOut(options.timeout))?
+    }
+
+    fn explain(&self, _options: &ExtractorOptions) -> Vec<Action> {
+        let mut actions = Vec::default();
+        if env::var_os("DOCKER_HOST").is_none() {
+            actions.push(Action::CheckFile(PathBuf::from(DOCKER_SOCKET)));
+        }
+
+        if !docker_available() {
+            actions.push(Action::Skip("Docker is not available".to_string()));
+            return actions;
+        }
+
+        let host = env::var("DOCKER_HOST").unwrap_or_else(|_| format!("unix://{DOCKER_SOCKET}"));
+        actions.push(Action::DockerApi(format!(
+            "GET /containers/json?all=true on {host}, looking for images {}*",
+            self.image_name
+        )));
+
+        actions
     }
 
     fn extractor_name() -> &'static str {
@@ -0,0 +1,149 @@
+// SPDX-FileCopyrightText: 2024 Benedikt Bastin
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use super::privilege::PrivilegeBackend;
+
+/// `PATH` of binaries run by extractors unless configured otherwise.
+pub const DEFAULT_PATH: &str = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
+
+const PRLIMIT: &str = "/usr/bin/prlimit";
+const SETPRIV: &str = "/usr/bin/setpriv";
+
+/// Resource limits of binaries run by extractors, applied with `prlimit`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct ResourceLimits {
+    /// Seconds of CPU time
+    pub cpu: Option<u64>,
+    /// Bytes of virtual memory
+    pub memory: Option<u64>,
+    pub open_files: Option<u64>,
+}
+
+impl ResourceLimits {
+    fn arguments(&self) -> Vec<OsString> {
+        [
+            ("--cpu", self.cpu),
+            ("--as", self.memory),
+            ("--nofile", self.open_files),
+        ]
+        .into_iter()
+        .filter_map(|(option, limit)| limit.map(|limit| format!("{option}={limit}").into()))
+        .collect()
+    }
+}
+
+/// The environment binaries from the database are run in. They neither see
+/// our environment variables nor our working directory or stdin.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ExecutionEnvironment {
+    /// The only environment variables besides `LANG=C` and `LC_ALL=C`
+    pub path: String,
+    pub working_directory: PathBuf,
+    /// Runs binaries through `setpriv --no-new-privs`
+    pub no_new_privileges: bool,
+    pub limits: ResourceLimits,
+    /// Used for extractors with a `user`
+    pub privilege_backend: PrivilegeBackend,
+}
+
+impl Default for ExecutionEnvironment {
+    fn default() -> Self {
+        ExecutionEnvironment {
+            path: DEFAULT_PATH.to_string(),
+            working_directory: PathBuf::from("/"),
+            no_new_privileges: false,
+            limits: ResourceLimits::default(),
+            privilege_backend: PrivilegeBackend::default(),
+        }
+    }
+}
+
+impl ExecutionEnvironment {
+    /// The program and arguments running `binary` with the configured
+    /// limits and privileges.
+    #[must_use]
+    pub fn wrap(&self, binary: &Path, arguments: &[String]) -> (PathBuf, Vec<OsString>) {
+        let mut command: Vec<OsString> = Vec::default();
+
+        let limits = self.limits.arguments();
+        if !limits.is_empty() {
+            command.push(PRLIMIT.into());
+            command.extend(limits);
+            command.push("--".into());
+        }
+
+        if self.no_new_privileges {
+            command.push(SETPRIV.into());
+            command.push("--no-new-privs".into());
+            command.push("--".into());
+        }
+
+        command.push(binary.into());
+        command.extend(arguments.iter().map(OsString::from));
+
+        let program = PathBuf::from(command.remove(0));
+        (program, command)
+    }
+
+    /// Clears the environment of `command` and detaches it from our stdin
+    /// and working directory.
+    pub fn apply(&self, command: &mut Command) {
+        command
+            .env_clear()
+            .env("PATH", &self.path)
+            .env("LANG", "C")
+            .env("LC_ALL", "C")
+            .current_dir(&self.working_directory)
+            .stdin(Stdio::null());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrapped_commands() {
+        let binary = Path::new("/usr/bin/psql");
+        let arguments = ["--version".to_string()];
+
+        let mut environment = ExecutionEnvironment::default();
+        assert_eq!(
+            environment.wrap(binary, &arguments),
+            (binary.to_path_buf(), vec!["--version".into()])
+        );
+
+        environment.no_new_privileges = true;
+        environment.limits = ResourceLimits {
+            cpu: Some(5),
+            memory: None,
+            open_files: Some(64),
+        };
+        assert_eq!(
+            environment.wrap(binary, &arguments),
+            (
+                PathBuf::from(PRLIMIT),
+                [
+                    "--cpu=5",
+                    "--nofile=64",
+                    "--",
+                    SETPRIV,
+                    "--no-new-privs",
+                    "--",
+                    "/usr/bin/psql",
+                    "--version"
+                ]
+                .into_iter()
+                .map(OsString::from)
+                .collect()
+            )
+        );
+    }
+}
@@ -0,0 +1,84 @@
+// SPDX-FileCopyrightText: 2024 Benedikt Bastin
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::{fs, io, path::PathBuf};
+
+const PASSWD: &str = "/etc/passwd";
+
+/// An entry of `/etc/passwd`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct User {
+    pub name: String,
+    pub uid: u32,
+    /// Primary group
+    pub gid: u32,
+    pub home: PathBuf,
+}
+
+/// The users of `/etc/passwd`. Users of other name services, e.g. LDAP, are
+/// not included.
+pub fn users() -> io::Result<Vec<User>> {
+    Ok(parse(&fs::read_to_string(PASSWD)?))
+}
+
+/// The user called `name`, if `/etc/passwd` contains it.
+#[must_use]
+pub fn user(name: &str) -> Option<User> {
+    users().ok()?.into_iter().find(|user| user.name == name)
+}
+
+/// Skips malformed lines.
+fn parse(passwd: &str) -> Vec<User> {
+    passwd
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<_> = line.split(':').collect();
+            let [name, _password, uid, gid, _gecos, home, _shell] = fields[..] else {
+                return None;
+            };
+
+            Some(User {
+                name: name.to_string(),
+                uid: uid.parse().ok()?,
+                gid: gid.parse().ok()?,
+                home: PathBuf::from(home),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_passwd() {
+        let users = parse(
+            "root:x:0:0:root:/root:/bin/bash
+postgres:x:104:110:PostgreSQL administrator,,,:/var/lib/postgresql:/bin/bash
+
+broken:x:1000
+nobody:x:65534:invalid:nobody:/nonexistent:/usr/sbin/nologin
+",
+        );
+
+        assert_eq!(
+            users,
+            [
+                User {
+                    name: "root".to_string(),
+                    uid: 0,
+                    gid: 0,
+                    home: PathBuf::from("/root"),
+                },
+                User {
+                    name: "postgres".to_string(),
+                    uid: 104,
+                    gid: 110,
+                    home: PathBuf::from("/var/lib/postgresql"),
+                }
+            ]
+        );
+    }
+}
@@ -0,0 +1,205 @@
+// SPDX-FileCopyrightText: 2024 Benedikt Bastin
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::{ffi::OsString, path::Path, process::Command};
+
+use rustix::process::geteuid;
+use serde::{Deserialize, Serialize};
+
+use super::{passwd, ExtractorError};
+
+/// How binaries of extractors with a `user` are run as that user.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum PrivilegeBackend {
+    /// `sudo`, failing instead of asking for a password
+    #[default]
+    Sudo,
+    /// `runuser`, which only works as root
+    Runuser,
+    /// `setpriv`, which only works as root
+    Setpriv,
+    /// A transient systemd service
+    SystemdRun,
+    /// `runuser` if we are root, otherwise the binary counts as not installed
+    SkipUnlessRoot,
+}
+
+impl PrivilegeBackend {
+    /// Whether binaries that run as another user are run at all.
+    #[must_use]
+    pub fn switches_users(self) -> bool {
+        self != PrivilegeBackend::SkipUnlessRoot || geteuid().is_root()
+    }
+
+    /// The command running `program` with `arguments` as `user`.
+    pub fn command(
+        self,
+        user: &str,
+        program: &Path,
+        arguments: &[OsString],
+    ) -> Result<Command, ExtractorError> {
+        let mut command = match self {
+            PrivilegeBackend::Sudo => {
+                let mut command = Command::new("/usr/bin/sudo");
+                command.args(["--non-interactive", "--user", user, "--"]);
+                command
+            }
+            PrivilegeBackend::Runuser => Self::runuser(user),
+            PrivilegeBackend::Setpriv => {
+                let primary_group = passwd::user(user)
+                    .ok_or_else(|| ExtractorError::UnknownUser(user.to_string()))?
+                    .gid;
+
+                let mut command = Command::new("/usr/bin/setpriv");
+                command
+                    .arg(format!("--reuid={user}"))
+                    .arg(format!("--regid={primary_group}"))
+                    .args(["--init-groups", "--"]);
+                command
+            }
+            PrivilegeBackend::SystemdRun => {
+                let mut command = Command::new("/usr/bin/systemd-run");
+                command
+                    .args([
+                        "--pipe",
+                        "--wait",
+                        "--collect",
+                        "--quiet",
+                        "--no-ask-password",
+                        "--service-type=exec",
+                    ])
+                    .arg(format!("--uid={user}"))
+                    .arg("--");
+                command
+            }
+            PrivilegeBackend::SkipUnlessRoot if geteuid().is_root() => Self::runuser(user),
+            PrivilegeBackend::SkipUnlessRoot => {
+                return Err(ExtractorError::PermissionRequired(user.to_string()));
+            }
+        };
+
+        command.arg(program).args(arguments);
+        Ok(command)
+    }
+
+    fn runuser(user: &str) -> Command {
+        let mut command = Command::new("/usr/sbin/runuser");
+        command.args(["-u", user, "--"]);
+        command
+    }
+
+    /// Whether `stderr` of a failed command says that we lack the permission
+    /// to switch users, e.g. because sudo wants a password.
+    #[must_use]
+    pub fn permission_denied(self, stderr: &str) -> bool {
+        let contains_any =
+            |messages: &[&str]| messages.iter().any(|message| stderr.contains(message));
+
+        match self {
+            PrivilegeBackend::Sudo => contains_any(&[
+                "a password is required",
+                "a terminal is required",
+                "is not in the sudoers file",
+                "is not allowed to execute",
+                "may not run sudo",
+            ]),
+            PrivilegeBackend::Runuser | PrivilegeBackend::SkipUnlessRoot => {
+                contains_any(&["may not be used by non-root users"])
+            }
+            // Only failures of setpriv itself, e.g. `setpriv: setresuid
+            // failed: Operation not permitted`, not those of the binary.
+            PrivilegeBackend::Setpriv => stderr.lines().any(|line| {
+                line.starts_with("setpriv: ") && line.ends_with("Operation not permitted")
+            }),
+            PrivilegeBackend::SystemdRun => {
+                contains_any(&["Interactive authentication required", "Access denied"])
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command_line(command: &Command) -> Vec<String> {
+        std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|part| part.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn privileged_commands() {
+        let program = Path::new("/usr/bin/psql");
+        let arguments = [OsString::from("--version")];
+
+        let sudo = PrivilegeBackend::Sudo
+            .command("postgres", program, &arguments)
+            .unwrap();
+        assert_eq!(
+            command_line(&sudo),
+            [
+                "/usr/bin/sudo",
+                "--non-interactive",
+                "--user",
+                "postgres",
+                "--",
+                "/usr/bin/psql",
+                "--version"
+            ]
+        );
+
+        let setpriv = PrivilegeBackend::Setpriv
+            .command("root", program, &arguments)
+            .unwrap();
+        assert_eq!(
+            command_line(&setpriv),
+            [
+                "/usr/bin/setpriv",
+                "--reuid=root",
+                "--regid=0",
+                "--init-groups",
+                "--",
+                "/usr/bin/psql",
+                "--version"
+            ]
+        );
+        assert!(matches!(
+            PrivilegeBackend::Setpriv.command("no-such-user", program, &arguments),
+            Err(ExtractorError::UnknownUser(_))
+        ));
+
+        assert!(PrivilegeBackend::Sudo.switches_users());
+        assert_eq!(
+            PrivilegeBackend::SkipUnlessRoot.switches_users(),
+            geteuid().is_root()
+        );
+        let skip = PrivilegeBackend::SkipUnlessRoot.command("postgres", program, &arguments);
+        if geteuid().is_root() {
+            assert_eq!(
+                command_line(&skip.unwrap())[..4],
+                ["/usr/sbin/runuser", "-u", "postgres", "--"]
+            );
+        } else {
+            assert!(matches!(skip, Err(ExtractorError::PermissionRequired(_))));
+        }
+    }
+
+    #[test]
+    fn denied_permissions() {
+        assert!(PrivilegeBackend::Sudo.permission_denied("sudo: a password is required\n"));
+        assert!(!PrivilegeBackend::Sudo.permission_denied("psql: error: connection failed\n"));
+        assert!(PrivilegeBackend::Runuser
+            .permission_denied("runuser: may not be used by non-root users\n"));
+        assert!(PrivilegeBackend::Setpriv
+            .permission_denied("setpriv: setresuid failed: Operation not permitted\n"));
+        assert!(!PrivilegeBackend::Setpriv
+            .permission_denied("psql: could not open file: Operation not permitted\n"));
+        assert!(PrivilegeBackend::SystemdRun.permission_denied(
+            "Failed to start transient service unit: Interactive authentication required."
+        ));
+    }
+}
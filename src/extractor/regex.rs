@@ -2,65 +2,180 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use std::fmt;
+
 use log::info;
 use regex::Regex;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::program::Version;
+use crate::program::{Version, VersionFormat};
 
 use super::ExtractorError;
 
-pub fn parse_version(input: &str, regex: &str) -> Result<Version, ExtractorError> {
-    info!(r#"Applying "{regex}" to "{input}""#);
+/// A regex that is compiled and checked for the required capture groups
+/// once, when the database is loaded. It is (de)serialized as its pattern.
+#[derive(Clone, Debug)]
+pub struct VersionRegex(Regex);
 
-    let re = Regex::new(regex)?;
-    let matcher = re.captures(input);
-    if matcher.is_none() {
-        return Err(ExtractorError::VersionError(
-            "Regex did not match".to_owned(),
-        ));
+impl VersionRegex {
+    pub const REQUIRED_GROUPS: [&'static str; 1] = ["version"];
+
+    pub fn new(regex: &str) -> Result<Self, ExtractorError> {
+        let re = Regex::new(regex)?;
+
+        for group in Self::REQUIRED_GROUPS {
+            if !re.capture_names().flatten().any(|name| name == group) {
+                return Err(ExtractorError::MissingCaptureGroup(group));
+            }
+        }
+
+        Ok(VersionRegex(re))
     }
-    let caps = matcher.unwrap();
 
-    let whole_version = caps.name("version").unwrap().as_str().to_string();
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
 
-    let cycle = caps.name("cycle").unwrap().as_str().to_string();
+impl PartialEq for VersionRegex {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
 
-    let major = caps.name("major").unwrap().as_str().parse::<usize>()?;
+impl Eq for VersionRegex {}
 
-    let minor = if let Some(minor) = caps.name("minor") {
-        Some(minor.as_str().parse::<usize>()?)
-    } else {
-        None
-    };
+impl Serialize for VersionRegex {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for VersionRegex {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let regex = String::deserialize(deserializer)?;
+
+        VersionRegex::new(&regex).map_err(de::Error::custom)
+    }
+}
+
+/// An ordered list of fallback regexes. In the database it is either a
+/// single pattern or an array of patterns.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct VersionRegexes(pub Vec<VersionRegex>);
+
+impl Serialize for VersionRegexes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0.as_slice() {
+            [regex] => regex.serialize(serializer),
+            regexes => serializer.collect_seq(regexes),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for VersionRegexes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(VersionRegexesVisitor)
+    }
+}
+
+/// Unlike an untagged enum, this keeps the error of an invalid pattern.
+struct VersionRegexesVisitor;
+
+impl<'de> de::Visitor<'de> for VersionRegexesVisitor {
+    type Value = VersionRegexes;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a regex or an array of regexes")
+    }
+
+    fn visit_str<E: de::Error>(self, regex: &str) -> Result<Self::Value, E> {
+        VersionRegex::new(regex)
+            .map(VersionRegexes::from)
+            .map_err(de::Error::custom)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut regexes = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+        while let Some(regex) = seq.next_element()? {
+            regexes.push(regex);
+        }
+
+        if regexes.is_empty() {
+            return Err(de::Error::invalid_length(0, &self));
+        }
+
+        Ok(VersionRegexes(regexes))
+    }
+}
+
+impl From<VersionRegex> for VersionRegexes {
+    fn from(regex: VersionRegex) -> Self {
+        VersionRegexes(vec![regex])
+    }
+}
+
+/// Applies `regexes` in order, the first one that yields a valid version
+/// wins. If none does, the error of the last one is returned.
+pub fn parse_first_version(
+    input: &str,
+    regexes: &VersionRegexes,
+    format: &VersionFormat,
+) -> Result<Version, ExtractorError> {
+    let mut last_error = ExtractorError::VersionError("No regex given".to_owned());
+
+    for (index, regex) in regexes.0.iter().enumerate() {
+        match parse_version(input, regex, format) {
+            Ok(version) => {
+                info!(r#"Regex {index} "{}" matched"#, regex.as_str());
+                return Ok(version);
+            }
+            Err(error) => last_error = error,
+        }
+    }
+
+    Err(last_error)
+}
+
+/// Locates the `version` and the optional `cycle` capture groups of `regex`
+/// in `input` and parses the version according to `format`.
+pub fn parse_version(
+    input: &str,
+    regex: &VersionRegex,
+    format: &VersionFormat,
+) -> Result<Version, ExtractorError> {
+    info!(r#"Applying "{}" to "{input}""#, regex.as_str());
+
+    let caps = regex
+        .0
+        .captures(input)
+        .ok_or_else(|| ExtractorError::VersionError("Regex did not match".to_owned()))?;
 
-    let patch = if let Some(patch) = caps.name("patch") {
-        Some(patch.as_str().parse::<usize>()?)
-    } else {
-        None
+    // The groups exist, but may not have participated in the match.
+    let group = |name: &'static str| {
+        caps.name(name)
+            .map(|group| group.as_str())
+            .ok_or(ExtractorError::UnmatchedCaptureGroup(name))
     };
 
-    let extra = caps.name("extra").map(|extra| extra.as_str().to_string());
+    let cycle = caps.name("cycle").map(|cycle| cycle.as_str());
 
-    Ok(Version {
-        string: whole_version,
-        cycle,
-        major,
-        minor,
-        patch,
-        extra,
-    })
+    Ok(format.parse(group("version")?, cycle)?)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{program::CycleTemplate, version_scheme::VersionScheme};
 
     #[test]
     fn nginx() {
         let input = "nginx version: nginx/1.18.0 (Ubuntu)";
-        let regex = r"^nginx version: nginx/(?<version>(?<cycle>(?<major>\d+)\.(?<minor>\d+))\.(?<patch>\d+))";
+        let regex = VersionRegex::new(r"^nginx version: nginx/(?<version>(?<cycle>(?<major>\d+)\.(?<minor>\d+))\.(?<patch>\d+))").unwrap();
 
-        let version = parse_version(input, regex).expect("Could not extract version");
+        let version = parse_version(input, &regex, &VersionScheme::Semver.into())
+            .expect("Could not extract version");
 
         assert_eq!(version.string, "1.18.0".to_owned());
         assert_eq!(version.cycle, "1.18".to_owned());
@@ -77,9 +192,13 @@ Build Number: 9962179521
 Build Date: Wed Jul 17 13:56:52 UTC 2024
 Build Hash: 7bbf7ec130487af9a324040259b2e942d7b9ba3c
 Build Enterprise Ready: false";
-        let regex = r"^Version: (?<version>(?<cycle>(?<major>\d+)\.(?<minor>\d+))\.(?<patch>\d+))";
+        let regex = VersionRegex::new(
+            r"^Version: (?<version>(?<cycle>(?<major>\d+)\.(?<minor>\d+))\.(?<patch>\d+))",
+        )
+        .unwrap();
 
-        let version = parse_version(input, regex).expect("Could not extract version");
+        let version = parse_version(input, &regex, &VersionScheme::Semver.into())
+            .expect("Could not extract version");
 
         assert_eq!(version.string, "9.9.2".to_owned());
         assert_eq!(version.cycle, "9.9".to_owned());
@@ -88,4 +207,126 @@ Build Enterprise Ready: false";
         assert_eq!(version.patch, Some(2));
         assert_eq!(version.extra, None);
     }
+
+    #[test]
+    fn debian_package() {
+        let input = "Server version: Apache/2.4.57 (Debian)\nPackage: 2.4.57-2+deb12u1";
+        let regex =
+            VersionRegex::new(r"(?m)^Package: (?<version>(?<cycle>\d+\.\d+)\.\d+-\S+)").unwrap();
+
+        let version = parse_version(input, &regex, &VersionScheme::Debian.into())
+            .expect("Could not extract version");
+
+        assert_eq!(version.string, "2.4.57-2+deb12u1".to_owned());
+        assert_eq!(version.cycle, "2.4".to_owned());
+        assert_eq!(version.major, 2);
+        assert_eq!(version.minor, Some(4));
+        assert_eq!(version.patch, Some(57));
+        assert_eq!(version.extra, Some("-2+deb12u1".to_owned()));
+    }
+
+    #[test]
+    fn missing_groups() {
+        assert!(matches!(
+            VersionRegex::new(r"^(?<cycle>\d+)"),
+            Err(ExtractorError::MissingCaptureGroup("version"))
+        ));
+        assert!(matches!(
+            VersionRegex::new(r"^(?<version>(?<cycle>\d+)"),
+            Err(ExtractorError::RegexError(_))
+        ));
+
+        let regex = VersionRegex::new(r"^(?<version>(?<cycle>\d+))(?<extra>x)?|^v").unwrap();
+        assert!(matches!(
+            parse_version("v", &regex, &VersionScheme::Semver.into()),
+            Err(ExtractorError::UnmatchedCaptureGroup("version"))
+        ));
+    }
+
+    #[test]
+    fn serialization() {
+        let json = r#""^(?<version>(?<cycle>\\d+))""#;
+        let regex: VersionRegex = serde_json::from_str(json).expect("Deserialization failed");
+
+        assert_eq!(regex.as_str(), r"^(?<version>(?<cycle>\d+))");
+        assert_eq!(serde_json::to_string(&regex).unwrap(), json);
+
+        assert!(serde_json::from_str::<VersionRegex>(r#""^(?<cycle>\\d+)""#).is_err());
+    }
+
+    #[test]
+    fn fallbacks() {
+        let regexes: VersionRegexes = serde_json::from_str(
+            r#"[
+                "^openjdk version \"(?<version>(?<cycle>\\d+)\\.\\d+\\.\\d+)\"",
+                "^java version \"(?<version>(?<cycle>\\d+)\\.\\d+\\.\\d+)\""
+            ]"#,
+        )
+        .expect("Deserialization failed");
+        assert_eq!(regexes.0.len(), 2);
+
+        let version = parse_first_version(
+            r#"java version "21.0.4" 2024-07-16 LTS"#,
+            &regexes,
+            &VersionScheme::Semver.into(),
+        )
+        .expect("Could not extract version");
+        assert_eq!(version.string, "21.0.4");
+        assert_eq!(version.cycle, "21");
+
+        assert!(parse_first_version("unknown", &regexes, &VersionScheme::Semver.into()).is_err());
+        assert!(parse_first_version(
+            "unknown",
+            &VersionRegexes(vec![]),
+            &VersionScheme::Semver.into()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn single_or_many() {
+        let single = r#""^(?<version>(?<cycle>\\d+))""#;
+        let one: VersionRegexes = serde_json::from_str(single).expect("Deserialization failed");
+
+        assert_eq!(one.0.len(), 1);
+        assert_eq!(serde_json::to_string(&one).unwrap(), single);
+
+        let many = format!("[{single},{single}]");
+        let two: VersionRegexes = serde_json::from_str(&many).expect("Deserialization failed");
+
+        assert_eq!(two.0.len(), 2);
+        assert_eq!(serde_json::to_string(&two).unwrap(), many);
+    }
+
+    #[test]
+    fn invalid_regex_errors() {
+        let missing_group = r#""^(?<cycle>\\d+)""#;
+
+        for json in [missing_group.to_string(), format!("[{missing_group}]")] {
+            let error = serde_json::from_str::<VersionRegexes>(&json).unwrap_err();
+            assert!(
+                error
+                    .to_string()
+                    .contains(r#"missing the required capture group "version""#),
+                "{error}"
+            );
+        }
+
+        assert!(serde_json::from_str::<VersionRegexes>("[]").is_err());
+    }
+
+    #[test]
+    fn cycle_template() {
+        let regex = VersionRegex::new(r"^Mozilla Firefox (?<version>\d+\.\d+(?:\.\d+)?)").unwrap();
+        let format = VersionFormat {
+            scheme: VersionScheme::Semver,
+            cycle_template: Some(CycleTemplate::new("{major}").unwrap()),
+        };
+
+        let version = parse_version("Mozilla Firefox 128.0.3", &regex, &format)
+            .expect("Could not extract version");
+
+        assert_eq!(version.string, "128.0.3");
+        assert_eq!(version.cycle, "128");
+    }
 }
@@ -0,0 +1,291 @@
+// SPDX-FileCopyrightText: 2024 Benedikt Bastin
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::{
+    collections::HashSet,
+    fs,
+    hash::BuildHasher,
+    os::unix::fs::{MetadataExt, PermissionsExt},
+    path::{Path, PathBuf},
+};
+
+use log::warn;
+use rustix::process::geteuid;
+
+use super::passwd;
+
+/// How the `path` of a binary extractor is resolved.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BinaryLocation<'a> {
+    /// A single path, e.g. `/usr/bin/python3`
+    Path(&'a Path),
+    /// A glob pattern, e.g. `/usr/lib/jvm/*/bin/java`
+    Pattern(&'a str),
+    /// A name looked up on `PATH` and the search paths, e.g. `python3`
+    Name(&'a str),
+}
+
+impl<'a> BinaryLocation<'a> {
+    #[must_use]
+    pub fn new(path: &'a Path) -> Self {
+        let Some(text) = path.to_str() else {
+            return BinaryLocation::Path(path);
+        };
+
+        if !text.contains('/') {
+            BinaryLocation::Name(text)
+        } else if text.contains(['*', '?', '[']) {
+            BinaryLocation::Pattern(text)
+        } else {
+            BinaryLocation::Path(path)
+        }
+    }
+}
+
+/// Where binaries are looked for, see [`BinaryLocation`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SearchPaths<'a> {
+    /// `PATH` of the execution environment
+    pub path: &'a str,
+    /// Searched after `PATH`, may contain glob patterns
+    pub extra: &'a [String],
+}
+
+impl SearchPaths<'_> {
+    /// The existing directories, in the order they are searched.
+    fn directories(&self) -> Vec<PathBuf> {
+        let path = self
+            .path
+            .split(':')
+            .filter(|directory| !directory.is_empty())
+            .map(PathBuf::from);
+        let extra = self.extra.iter().flat_map(|pattern| expand(pattern));
+
+        path.chain(extra)
+            .filter(|directory| directory.is_dir())
+            .collect()
+    }
+
+    /// Every distinct installation of the binary at `location`. Paths that
+    /// lead to the same file, e.g. through symlinks, are only returned once.
+    #[must_use]
+    pub fn installations(&self, location: BinaryLocation) -> Vec<PathBuf> {
+        let candidates = match location {
+            BinaryLocation::Path(path) => {
+                return if path.exists() {
+                    vec![path.to_path_buf()]
+                } else {
+                    Vec::default()
+                };
+            }
+            BinaryLocation::Pattern(pattern) => expand(pattern),
+            BinaryLocation::Name(name) => self
+                .directories()
+                .into_iter()
+                .map(|directory| directory.join(name))
+                .collect(),
+        };
+
+        distinct(
+            candidates.into_iter().filter(|path| is_executable(path)),
+            &mut HashSet::new(),
+        )
+    }
+}
+
+/// The executables matching the glob `pattern`, in which a leading `~`
+/// stands for the home directory of every user in `/etc/passwd`, e.g.
+/// `~/.nvm/versions/node/*/bin/node`.
+///
+/// They may belong to other users, see [`is_trusted`] before running them.
+#[must_use]
+pub fn discover(pattern: &str) -> Vec<PathBuf> {
+    let patterns = match pattern.strip_prefix("~/") {
+        Some(rest) => home_directories()
+            .iter()
+            .map(|home| format!("{}/{rest}", home.display()))
+            .collect(),
+        None => vec![pattern.to_string()],
+    };
+
+    patterns
+        .iter()
+        .flat_map(|expanded| expand(expanded))
+        .filter(|path| is_executable(path))
+        .collect()
+}
+
+fn home_directories() -> Vec<PathBuf> {
+    let users = passwd::users().unwrap_or_else(|error| {
+        warn!("Could not read the home directories of users: {error}");
+        Vec::default()
+    });
+
+    let mut homes: Vec<_> = users
+        .into_iter()
+        .map(|user| user.home)
+        .filter(|home| home.is_absolute() && home.is_dir())
+        .collect();
+    homes.sort();
+    homes.dedup();
+    homes
+}
+
+/// Whether the binary at `path` and every directory above it, before and
+/// after resolving symlinks, are owned by root or the effective user and are
+/// not writable by group or others. Otherwise, another user could make us
+/// run their binary, with our privileges. Directories owned by root with the
+/// sticky bit, like `/tmp`, are writable by others, but only the owner can
+/// replace their entries.
+#[must_use]
+pub fn is_trusted(path: &Path) -> bool {
+    let euid = geteuid().as_raw();
+    let Ok(canonical) = fs::canonicalize(path) else {
+        return false;
+    };
+
+    path.ancestors()
+        .chain(canonical.ancestors())
+        .filter(|ancestor| !ancestor.as_os_str().is_empty())
+        .all(|ancestor| {
+            fs::metadata(ancestor).is_ok_and(|metadata| {
+                let sticky = metadata.is_dir() && metadata.mode() & 0o1000 != 0;
+                (metadata.uid() == 0 || metadata.uid() == euid)
+                    && (metadata.mode() & 0o022 == 0 || (sticky && metadata.uid() == 0))
+            })
+        })
+}
+
+/// The paths of `paths` not leading to a file in `seen`, which is extended
+/// by them.
+pub fn distinct<S: BuildHasher>(
+    paths: impl IntoIterator<Item = PathBuf>,
+    seen: &mut HashSet<PathBuf, S>,
+) -> Vec<PathBuf> {
+    paths
+        .into_iter()
+        .filter(|path| seen.insert(fs::canonicalize(path).unwrap_or_else(|_| path.clone())))
+        .collect()
+}
+
+fn expand(pattern: &str) -> Vec<PathBuf> {
+    match glob::glob(pattern) {
+        Ok(paths) => paths.filter_map(Result::ok).collect(),
+        Err(error) => {
+            warn!("Invalid glob pattern {pattern:?}: {error}");
+            Vec::default()
+        }
+    }
+}
+
+fn is_executable(path: &Path) -> bool {
+    fs::metadata(path)
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::symlink;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn write_binary(path: &Path, mode: u32) {
+        fs::create_dir_all(path.parent().unwrap()).expect("Could not create directory");
+        fs::write(path, "#!/bin/sh\n").expect("Could not write binary");
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))
+            .expect("Could not set permission on binary");
+    }
+
+    #[test]
+    fn binary_locations() {
+        assert_eq!(
+            BinaryLocation::new(Path::new("/usr/bin/python3")),
+            BinaryLocation::Path(Path::new("/usr/bin/python3"))
+        );
+        assert_eq!(
+            BinaryLocation::new(Path::new("/usr/lib/jvm/*/bin/java")),
+            BinaryLocation::Pattern("/usr/lib/jvm/*/bin/java")
+        );
+        assert_eq!(
+            BinaryLocation::new(Path::new("python3")),
+            BinaryLocation::Name("python3")
+        );
+    }
+
+    #[test]
+    fn found_installations() {
+        let tmp_dir = TempDir::new().expect("Could not create tmpdir");
+        let root = tmp_dir.path();
+        write_binary(&root.join("usr/bin/python3.12"), 0o755);
+        symlink("python3.12", root.join("usr/bin/python3")).expect("Could not create symlink");
+        write_binary(&root.join("usr/local/bin/python3"), 0o755);
+        write_binary(&root.join("opt/python3.8/bin/python3"), 0o755);
+        write_binary(&root.join("opt/broken/bin/python3"), 0o644);
+
+        let path = format!(
+            "{}:{}:{}",
+            root.join("usr/local/bin").display(),
+            root.join("missing").display(),
+            root.join("usr/bin").display()
+        );
+        let extra = [format!("{}/opt/*/bin", root.display())];
+        let search_paths = SearchPaths {
+            path: &path,
+            extra: &extra,
+        };
+
+        assert_eq!(
+            search_paths.installations(BinaryLocation::Name("python3")),
+            [
+                root.join("usr/local/bin/python3"),
+                root.join("usr/bin/python3"),
+                root.join("opt/python3.8/bin/python3")
+            ]
+        );
+
+        let pattern = format!("{}/usr/bin/python3*", root.display());
+        assert_eq!(
+            search_paths.installations(BinaryLocation::Pattern(&pattern)),
+            [root.join("usr/bin/python3")]
+        );
+
+        assert!(search_paths
+            .installations(BinaryLocation::Path(&root.join("usr/bin/python2")))
+            .is_empty());
+    }
+
+    #[test]
+    fn trusted_binaries() {
+        let tmp_dir = TempDir::new().expect("Could not create tmpdir");
+        let root = tmp_dir.path();
+        write_binary(&root.join("trusted/bin/node"), 0o755);
+        write_binary(&root.join("writable/bin/node"), 0o757);
+        write_binary(&root.join("shared/bin/node"), 0o755);
+        fs::set_permissions(root.join("shared"), fs::Permissions::from_mode(0o775))
+            .expect("Could not set permission on directory");
+        symlink(
+            root.join("writable/bin/node"),
+            root.join("trusted/bin/link"),
+        )
+        .expect("Could not create symlink");
+
+        // Untrusted binaries are discovered, but must not be run.
+        let pattern = format!("{}/*/bin/*", root.display());
+        assert_eq!(discover(&pattern).len(), 4);
+
+        assert!(is_trusted(&root.join("trusted/bin/node")));
+        assert!(!is_trusted(&root.join("writable/bin/node")));
+        assert!(!is_trusted(&root.join("shared/bin/node")));
+        assert!(!is_trusted(&root.join("trusted/bin/link")));
+        assert!(!is_trusted(&root.join("trusted/bin/missing")));
+
+        if geteuid().is_root() {
+            std::os::unix::fs::chown(root.join("trusted/bin/node"), Some(65534), None)
+                .expect("Could not change owner");
+            assert!(!is_trusted(&root.join("trusted/bin/node")));
+        }
+    }
+}
@@ -4,9 +4,11 @@
 
 use std::error::Error;
 
+use serde::{Deserialize, Serialize};
+
 use crate::program::Version;
 
-#[derive(Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct VersionedProgramInfo {
     pub id: String,
     pub title: String,
@@ -14,5 +16,9 @@ pub struct VersionedProgramInfo {
 }
 
 pub trait HashDatabase {
-    fn get(&self, hash: &str) -> Result<Option<VersionedProgramInfo>, Box<dyn Error>>;
+    /// Failures of lookups, wrapped in
+    /// [`crate::scan::ScanError::HashDatabaseError`] by scans.
+    type Error: Error + Send + Sync + 'static;
+
+    fn get(&self, hash: &str) -> Result<Option<VersionedProgramInfo>, Self::Error>;
 }
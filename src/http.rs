@@ -0,0 +1,122 @@
+// SPDX-FileCopyrightText: 2024 Benedikt Bastin
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::time::Duration;
+
+use log::{info, warn};
+use reqwest::{Client, Response, StatusCode};
+
+/// The HTTP client shared by all requests, retrying failed requests with an
+/// exponential backoff.
+#[derive(Clone, Debug)]
+pub struct HttpClient {
+    client: Client,
+    retries: u32,
+    backoff: Duration,
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        HttpClient::new(Client::default())
+    }
+}
+
+impl HttpClient {
+    #[must_use]
+    pub fn new(client: Client) -> Self {
+        HttpClient {
+            client,
+            retries: 0,
+            backoff: Duration::from_secs(1),
+        }
+    }
+
+    /// Retries a failed request up to `retries` times, waiting `backoff`
+    /// before the first retry and twice as long before every further one.
+    #[must_use]
+    pub fn with_retries(mut self, retries: u32, backoff: Duration) -> Self {
+        self.retries = retries;
+        self.backoff = backoff;
+        self
+    }
+
+    /// Fails on error status codes as well.
+    pub async fn get(&self, url: &str) -> Result<Response, reqwest::Error> {
+        let mut delay = self.backoff;
+
+        for retry in 1..=self.retries {
+            match self.send(url).await {
+                Err(error) if Self::is_transient(&error) => {
+                    warn!(
+                        "Request to {url} failed ({error}), retry {retry}/{} in {delay:?}",
+                        self.retries
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                }
+                result => return result,
+            }
+        }
+
+        self.send(url).await
+    }
+
+    async fn send(&self, url: &str) -> Result<Response, reqwest::Error> {
+        let response = self.client.get(url).send().await?;
+        info!("Recieved response {} from {url}", response.status());
+
+        response.error_for_status()
+    }
+
+    fn is_transient(error: &reqwest::Error) -> bool {
+        match error.status() {
+            Some(status) => status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS,
+            None => error.is_timeout() || error.is_connect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn retries() {
+        let mut server = mockito::Server::new_async().await;
+        let client = HttpClient::default().with_retries(2, Duration::from_millis(1));
+
+        let unavailable = server
+            .mock("GET", "/unavailable")
+            .with_status(503)
+            .expect(3)
+            .create_async()
+            .await;
+        let missing = server
+            .mock("GET", "/missing")
+            .with_status(404)
+            .expect(1)
+            .create_async()
+            .await;
+        let available = server
+            .mock("GET", "/available")
+            .with_status(200)
+            .with_body("ok")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let url = server.url();
+        let error = client
+            .get(&format!("{url}/unavailable"))
+            .await
+            .expect_err("Request did not fail");
+        assert_eq!(error.status(), Some(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(client.get(&format!("{url}/missing")).await.is_err());
+        assert!(client.get(&format!("{url}/available")).await.is_ok());
+
+        unavailable.assert_async().await;
+        missing.assert_async().await;
+        available.assert_async().await;
+    }
+}
@@ -0,0 +1,438 @@
+// SPDX-FileCopyrightText: 2024 Benedikt Bastin
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::path::Path;
+
+use assetinfo::{
+    detection::{detect_stream, Detection, DetectionOptions, ProgramDetection},
+    extractor::ExtractorError,
+    program::{Program, Version},
+    providers::{endoflife_date::DateOrBool, Cycle, CycleResolver, LifecycleProvider},
+    version_scheme::VersionScheme,
+};
+use chrono::NaiveDate;
+use futures_util::StreamExt;
+use serde::Serialize;
+
+use crate::table_view::{get_display_release_cycle, get_releases_behind, SupportState};
+
+/// Incremented on incompatible changes of the output, which is described by
+/// `docs/output-v1.schema.json`.
+pub(crate) const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum JsonFormat {
+    /// A single document
+    Document,
+    /// One object per line, printed as soon as it is available
+    Lines,
+}
+
+/// An object of the output, tagged with the schema version in
+/// [`JsonFormat::Lines`].
+#[derive(Serialize)]
+struct Line<T> {
+    schema_version: u32,
+    #[serde(flatten)]
+    record: T,
+}
+
+#[derive(Serialize)]
+struct ProgramRecord<'a> {
+    id: &'a str,
+    title: &'a str,
+    binary: bool,
+    docker: bool,
+}
+
+#[derive(Serialize)]
+struct ProgramsDocument<'a> {
+    schema_version: u32,
+    programs: Vec<ProgramRecord<'a>>,
+}
+
+#[derive(Serialize)]
+struct VersionRecord<'a> {
+    string: &'a str,
+    scheme: VersionScheme,
+    major: usize,
+    minor: Option<usize>,
+    patch: Option<usize>,
+    extra: Option<&'a str>,
+    cycle: &'a str,
+}
+
+#[derive(Serialize)]
+struct CycleRecord<'a> {
+    cycle: &'a str,
+    release_date: Option<NaiveDate>,
+    latest: Option<&'a str>,
+    latest_release_date: Option<NaiveDate>,
+    lts: bool,
+    support: Option<DateOrBool>,
+    eol: DateOrBool,
+    extended_support: Option<DateOrBool>,
+    link: Option<&'a str>,
+    overridden: bool,
+}
+
+#[derive(Serialize)]
+struct ErrorRecord {
+    kind: &'static str,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct DetectionRecord<'a> {
+    program_id: &'a str,
+    title: &'a str,
+    extractor: String,
+    location: Option<&'a Path>,
+    version: Option<VersionRecord<'a>>,
+    release_cycle: Option<CycleRecord<'a>>,
+    releases_behind: usize,
+    support_state: &'static str,
+    error: Option<ErrorRecord>,
+}
+
+/// A detection in [`JsonFormat::Lines`], which has no surrounding document
+/// for the snapshot date.
+#[derive(Serialize)]
+struct DetectionLine<'a> {
+    snapshot_date: Option<NaiveDate>,
+    #[serde(flatten)]
+    detection: DetectionRecord<'a>,
+}
+
+#[derive(Serialize)]
+struct DetectionsDocument<'a> {
+    schema_version: u32,
+    snapshot_date: Option<NaiveDate>,
+    detections: Vec<DetectionRecord<'a>>,
+}
+
+pub(crate) fn list_supported_programs(programs: &[Program], format: JsonFormat) {
+    let mut records: Vec<_> = programs
+        .iter()
+        .map(|program| ProgramRecord {
+            id: &program.info.id,
+            title: &program.info.title,
+            binary: program
+                .binary
+                .as_ref()
+                .is_some_and(|binary_extractors| !binary_extractors.is_empty()),
+            docker: program.docker.is_some(),
+        })
+        .collect();
+    records.sort_by(|lhs, rhs| lhs.title.cmp(rhs.title));
+
+    match format {
+        JsonFormat::Document => print_json(&ProgramsDocument {
+            schema_version: SCHEMA_VERSION,
+            programs: records,
+        }),
+        JsonFormat::Lines => {
+            for record in records {
+                print_json(&Line {
+                    schema_version: SCHEMA_VERSION,
+                    record,
+                });
+            }
+        }
+    }
+}
+
+/// Prints the detections of all installed `programs`. Returns the number of
+/// failed detections.
+pub(crate) async fn list_info(
+    programs: &[Program],
+    resolver: &CycleResolver,
+    options: &DetectionOptions,
+    format: JsonFormat,
+) -> usize {
+    let mut detections = detect_stream(programs, resolver, options)
+        .filter(|program_detection| {
+            let installed = !matches!(program_detection.detection, Detection::NotInstalled);
+            async move { installed }
+        })
+        .boxed_local();
+    let mut errors = 0;
+
+    match format {
+        JsonFormat::Document => {
+            let detections: Vec<_> = detections.collect().await;
+            errors = detections
+                .iter()
+                .filter(|program_detection| program_detection.detection.is_error())
+                .count();
+
+            print_json(&DetectionsDocument {
+                schema_version: SCHEMA_VERSION,
+                snapshot_date: resolver.provider().snapshot_date(),
+                detections: detections.iter().map(detection_record).collect(),
+            });
+        }
+        JsonFormat::Lines => {
+            let snapshot_date = resolver.provider().snapshot_date();
+
+            while let Some(program_detection) = detections.next().await {
+                if program_detection.detection.is_error() {
+                    errors += 1;
+                }
+
+                print_json(&Line {
+                    schema_version: SCHEMA_VERSION,
+                    record: DetectionLine {
+                        snapshot_date,
+                        detection: detection_record(&program_detection),
+                    },
+                });
+            }
+        }
+    }
+
+    errors
+}
+
+fn detection_record<'a>(program_detection: &'a ProgramDetection) -> DetectionRecord<'a> {
+    let ProgramDetection {
+        program,
+        source,
+        ref location,
+        ref detection,
+    } = *program_detection;
+
+    let (version, release_cycle) = match *detection {
+        Detection::Found {
+            ref version,
+            ref release_cycle,
+        } => (Some(version), release_cycle.as_ref()),
+        Detection::ProviderError { ref version, .. } => (Some(version), None),
+        Detection::NotInstalled | Detection::ExtractorError(_) => (None, None),
+    };
+
+    let releases_behind = version.map_or(0, |version| get_releases_behind(version, release_cycle));
+
+    DetectionRecord {
+        program_id: &program.info.id,
+        title: &program.info.title,
+        extractor: source.to_lowercase(),
+        location: location.as_deref(),
+        version: version.map(version_record),
+        release_cycle: release_cycle.map(cycle_record),
+        releases_behind,
+        support_state: support_state_name(get_display_release_cycle(
+            release_cycle,
+            releases_behind,
+        )),
+        error: error_record(detection),
+    }
+}
+
+fn version_record(version: &Version) -> VersionRecord<'_> {
+    VersionRecord {
+        string: &version.string,
+        scheme: version.scheme,
+        major: version.major,
+        minor: version.minor,
+        patch: version.patch,
+        extra: version.extra.as_deref(),
+        cycle: &version.cycle,
+    }
+}
+
+fn cycle_record(cycle: &Cycle) -> CycleRecord<'_> {
+    CycleRecord {
+        cycle: &cycle.cycle,
+        release_date: cycle.release_date,
+        latest: cycle.latest.as_deref(),
+        latest_release_date: cycle.latest_release_date,
+        lts: cycle.lts,
+        support: cycle.support,
+        eol: cycle.eol,
+        extended_support: cycle.extended_support,
+        link: cycle.link.as_deref(),
+        overridden: cycle.overridden,
+    }
+}
+
+fn error_record(detection: &Detection) -> Option<ErrorRecord> {
+    let kind = match *detection {
+        Detection::ExtractorError(ExtractorError::TimedOut(_)) => "timed_out",
+        Detection::ExtractorError(ExtractorError::PermissionRequired(_)) => "permission_required",
+        Detection::ExtractorError(ExtractorError::NotAllowed(_)) => "not_allowed",
+        Detection::ExtractorError(ExtractorError::NotTrusted(_)) => "not_trusted",
+        Detection::ExtractorError(_) => "extractor",
+        Detection::ProviderError { .. } => "lifecycle",
+        Detection::Found { .. } | Detection::NotInstalled => return None,
+    };
+
+    detection
+        .error_text()
+        .map(|message| ErrorRecord { kind, message })
+}
+
+fn support_state_name(state: SupportState) -> &'static str {
+    match state {
+        SupportState::Supported => "supported",
+        SupportState::Outdated => "outdated",
+        SupportState::Security => "security",
+        SupportState::ExtendedSupport => "extended_support",
+        SupportState::AlmostEol => "almost_eol",
+        SupportState::Unsupported => "unsupported",
+        SupportState::Unknown => "unknown",
+    }
+}
+
+fn print_json<T: Serialize>(value: &T) {
+    match serde_json::to_string(value) {
+        Ok(json) => println!("{json}"),
+        Err(error) => eprintln!("Could not serialize output: {error}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeSet, time::Duration};
+
+    use assetinfo::providers::ProviderError;
+    use serde_json::Value;
+
+    use super::*;
+
+    /// The `required` fields of `definition` in the output schema.
+    fn schema_fields(definition: &str) -> BTreeSet<String> {
+        let schema: Value = serde_json::from_str(include_str!("../docs/output-v1.schema.json"))
+            .expect("Invalid output schema");
+
+        schema["definitions"][definition]["required"]
+            .as_array()
+            .expect("Missing required fields")
+            .iter()
+            .map(|field| field.as_str().expect("Invalid field").to_string())
+            .collect()
+    }
+
+    fn fields(value: &Value) -> BTreeSet<String> {
+        value
+            .as_object()
+            .expect("Not an object")
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    fn program() -> Program {
+        serde_json::from_str(
+            r#"{
+                "info": {
+                    "id": "org.nginx",
+                    "title": "nginx",
+                    "endoflife_date_id": "nginx"
+                },
+                "binary": [],
+                "docker": null
+            }"#,
+        )
+        .expect("Invalid program")
+    }
+
+    fn detection_json(program: &Program, detection: Detection) -> Value {
+        let program_detection = ProgramDetection {
+            program,
+            source: "Binary",
+            location: Some("/usr/sbin/nginx".into()),
+            detection,
+        };
+
+        serde_json::to_value(detection_record(&program_detection)).expect("Serialization failed")
+    }
+
+    #[test]
+    fn detection_records() {
+        let program = program();
+        let version = Version::parse("1.26.1", "1.26", VersionScheme::Semver).unwrap();
+        let cycle: Cycle = serde_json::from_str(
+            r#"{
+                "cycle": "1.26",
+                "release_date": "2024-04-23",
+                "latest": "1.26.2",
+                "latest_release_date": "2024-08-14",
+                "eol": false,
+                "link": null
+            }"#,
+        )
+        .expect("Invalid cycle");
+
+        let found = detection_json(
+            &program,
+            Detection::Found {
+                version: version.clone(),
+                release_cycle: Some(cycle),
+            },
+        );
+        assert_eq!(fields(&found), schema_fields("detection"));
+        assert_eq!(fields(&found["version"]), schema_fields("version"));
+        assert_eq!(
+            fields(&found["release_cycle"]),
+            schema_fields("release_cycle")
+        );
+        assert_eq!(found["program_id"], "org.nginx");
+        assert_eq!(found["extractor"], "binary");
+        assert_eq!(found["location"], "/usr/sbin/nginx");
+        assert_eq!(found["version"]["scheme"], "semver");
+        assert_eq!(found["release_cycle"]["release_date"], "2024-04-23");
+        assert_eq!(found["releases_behind"], 1);
+        assert_eq!(found["error"], Value::Null);
+
+        let provider_error = detection_json(
+            &program,
+            Detection::ProviderError {
+                version,
+                error: ProviderError::InvalidId("../nginx".to_string()),
+            },
+        );
+        assert_eq!(fields(&provider_error), schema_fields("detection"));
+        assert_eq!(provider_error["version"]["string"], "1.26.1");
+        assert_eq!(provider_error["release_cycle"], Value::Null);
+        assert_eq!(provider_error["support_state"], "unknown");
+        assert_eq!(provider_error["error"]["kind"], "lifecycle");
+
+        let extractor_error = detection_json(
+            &program,
+            Detection::ExtractorError(ExtractorError::TimedOut(Duration::from_secs(5))),
+        );
+        assert_eq!(fields(&extractor_error), schema_fields("detection"));
+        assert_eq!(extractor_error["version"], Value::Null);
+        assert_eq!(extractor_error["error"]["kind"], "timed_out");
+        assert!(extractor_error["error"]["message"].is_string());
+    }
+
+    #[test]
+    fn detection_lines() {
+        let program = program();
+        let program_detection = ProgramDetection {
+            program: &program,
+            source: "Docker",
+            location: None,
+            detection: Detection::ExtractorError(ExtractorError::TimedOut(Duration::from_secs(5))),
+        };
+
+        let line = serde_json::to_value(Line {
+            schema_version: SCHEMA_VERSION,
+            record: DetectionLine {
+                snapshot_date: NaiveDate::from_ymd_opt(2024, 8, 10),
+                detection: detection_record(&program_detection),
+            },
+        })
+        .expect("Serialization failed");
+
+        let mut expected = schema_fields("detection");
+        expected.extend(["schema_version".to_string(), "snapshot_date".to_string()]);
+        assert_eq!(fields(&line), expected);
+        assert_eq!(line["schema_version"], SCHEMA_VERSION);
+        assert_eq!(line["snapshot_date"], "2024-08-10");
+        assert_eq!(line["extractor"], "docker");
+    }
+}
@@ -3,8 +3,12 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 pub mod db;
+pub mod detection;
+pub mod explain;
 pub mod extractor;
 pub mod hash_database;
+pub mod http;
 pub mod program;
 pub mod providers;
 pub mod scan;
+pub mod version_scheme;
@@ -4,18 +4,33 @@
 
 use assetinfo::{
     db::Database,
-    extractor::Extractor,
-    program::{Program, ProgramInfo, Version},
-    providers::endoflife_date::{self, DateOrBool, EndOfLifeDateClient, ReleaseCycle},
+    detection::{detect_all, error_chain, Detection, DetectionOptions, ProgramDetection},
+    explain::explain,
+    extractor::ExtractorError,
+    http::HttpClient,
+    program::{Program, Version},
+    providers::{
+        composite::CompositeProvider,
+        endoflife_date::{
+            cache::ResponseCache,
+            snapshot::{Snapshot, SNAPSHOT_FOLDER},
+            DateOrBool, EndOfLifeDateClient,
+        },
+        file::FileProvider,
+        policy::Policy,
+        Cycle, CycleResolver, LifecycleProvider,
+    },
 };
 use chrono::{TimeDelta, Utc};
-use clap::{Parser, Subcommand};
+use clap::{error::ErrorKind, CommandFactory, Parser, Subcommand, ValueEnum};
 use config::Config;
-use log::error;
-use std::{error::Error, fs, path::PathBuf, process::exit};
+use json_view::JsonFormat;
+use log::{error, warn};
+use std::{error::Error, fs, path::PathBuf, process::exit, time::Duration};
 
 mod about;
 mod config;
+mod json_view;
 mod table_view;
 
 #[derive(Parser, Debug)]
@@ -27,6 +42,43 @@ struct Args {
     /// Specify path to config file
     #[arg(long, default_value = "./assetinfo-config.toml")]
     config_file: PathBuf,
+
+    /// Only use cached endoflife.date responses or the database snapshot
+    #[arg(long, default_value = "false")]
+    offline: bool,
+
+    /// Exit with an error code if the detection of any program failed
+    #[arg(long, default_value = "false")]
+    strict: bool,
+
+    /// Only print the commands, file accesses and requests of `info` and
+    /// `info-all` instead of running them
+    #[arg(long, default_value = "false")]
+    dry_run: bool,
+
+    /// Format of the output of `list`, `info` and `info-all`
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum OutputFormat {
+    /// Tables and messages
+    Text,
+    /// A single JSON document
+    Json,
+    /// One JSON object per line, as soon as it is available
+    Ndjson,
+}
+
+impl OutputFormat {
+    fn json(self) -> Option<JsonFormat> {
+        match self {
+            OutputFormat::Text => None,
+            OutputFormat::Json => Some(JsonFormat::Document),
+            OutputFormat::Ndjson => Some(JsonFormat::Lines),
+        }
+    }
 }
 
 #[derive(Subcommand, Debug)]
@@ -35,10 +87,22 @@ enum Commands {
     List {},
 
     /// Get information for a program
-    Info { name: String },
+    Info {
+        name: String,
+
+        /// Also detect installations in version manager and alternative
+        /// directories, e.g. every JDK in /usr/lib/jvm
+        #[arg(long, default_value = "false")]
+        discover: bool,
+    },
 
     /// Get information for all supported programs
-    InfoAll {},
+    InfoAll {
+        /// Also detect installations in version manager and alternative
+        /// directories, e.g. every JDK in /usr/lib/jvm
+        #[arg(long, default_value = "false")]
+        discover: bool,
+    },
 
     /// Update internal database of supported programs
     Update {},
@@ -54,6 +118,15 @@ enum Commands {
 async fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
 
+    if args.dry_run && args.output.json().is_some() {
+        Args::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--dry-run only prints text and cannot be combined with --output json or ndjson",
+            )
+            .exit();
+    }
+
     let config = Config::load(args.config_file)?;
 
     simple_logger::init_with_level(config.log_level().unwrap_or(log::Level::Warn)).unwrap();
@@ -62,27 +135,66 @@ async fn main() -> Result<(), Box<dyn Error>> {
         Commands::List {} => {
             let db = Database::load(config.database_folder())?;
 
-            table_view::list_supported_programs(&db.supported_programs);
+            if let Some(format) = args.output.json() {
+                json_view::list_supported_programs(&db.supported_programs, format);
+            } else {
+                table_view::list_supported_programs(&db.supported_programs);
+            }
         }
-        Commands::Info { name } => {
+        Commands::Info { name, discover } => {
             let db = Database::load(config.database_folder())?;
 
-            let program = db.get(name.as_str());
-
-            if program.is_none() {
+            let Some(program) = db.get(name.as_str()) else {
                 println!("Could not find any program matching {name}");
                 exit(-1);
+            };
+            let programs = [program];
+            let mut options = config.detection_options()?;
+            options.extractor.discover |= discover;
+
+            let resolver = cycle_resolver(&config, http_client(&config)?, args.offline);
+
+            if args.dry_run {
+                print_explanations(&programs, &resolver, &options);
+                return Ok(());
             }
 
-            let _ = gather_program_info(program.unwrap()).await;
+            let errors = if let Some(format) = args.output.json() {
+                json_view::list_info(&programs, &resolver, &options, format).await
+            } else {
+                let errors = gather_program_info(&programs, &resolver, &options).await;
+                print_snapshot_date(&resolver);
+                errors
+            };
+
+            if args.strict && errors > 0 {
+                exit(1);
+            }
         }
-        Commands::InfoAll {} => {
+        Commands::InfoAll { discover } => {
             let db = Database::load(config.database_folder())?;
+            let mut options = config.detection_options()?;
+            options.extractor.discover |= discover;
 
-            // for program in db.supported_programs {
-            //     let _ = gather_program_info(program).await;
-            // }
-            table_view::list_info_all(db.supported_programs).await?;
+            let resolver = cycle_resolver(&config, http_client(&config)?, args.offline);
+
+            if args.dry_run {
+                print_explanations(&db.supported_programs, &resolver, &options);
+                return Ok(());
+            }
+
+            let errors = if let Some(format) = args.output.json() {
+                json_view::list_info(&db.supported_programs, &resolver, &options, format).await
+            } else {
+                let errors =
+                    table_view::list_info_all(&db.supported_programs, &resolver, &options).await;
+                print_snapshot_date(&resolver);
+                errors
+            };
+
+            if args.strict && errors > 0 {
+                exit(1);
+            }
         }
         Commands::Update {} => {
             update_database(&config).await?;
@@ -95,50 +207,217 @@ async fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-async fn gather_program_info(program: Program) -> Result<(), Box<dyn Error>> {
-    if let Some(binary_extractors) = program.binary {
-        for extractor in binary_extractors {
-            print_info(extractor, &program.info).await?;
+fn http_client(config: &Config) -> Result<HttpClient, Box<dyn Error>> {
+    let mut builder = reqwest::Client::builder();
+
+    if let Some(proxy) = config.proxy() {
+        builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+    }
+
+    if let Some(ca_bundle) = config.ca_bundle() {
+        for certificate in reqwest::Certificate::from_pem_bundle(&fs::read(ca_bundle)?)? {
+            builder = builder.add_root_certificate(certificate);
         }
     }
 
-    if let Some(extractor) = program.docker {
-        print_info(extractor, &program.info).await?;
+    if let Some(timeout) = config.timeout() {
+        builder = builder.timeout(timeout);
     }
 
-    Ok(())
+    Ok(HttpClient::new(builder.build()?).with_retries(config.retries(), Duration::from_secs(1)))
+}
+
+fn cycle_resolver(config: &Config, http: HttpClient, offline: bool) -> CycleResolver {
+    let mut client = EndOfLifeDateClient::new(config.endoflife_date_url(), http).offline(offline);
+
+    if let Some(cache_folder) = config.cache_folder() {
+        client = client.with_cache(ResponseCache::new(
+            cache_folder.to_path_buf(),
+            config.cache_ttl(),
+        ));
+    }
+
+    match Snapshot::load(&config.database_folder().join(SNAPSHOT_FOLDER)) {
+        Ok(Some(snapshot)) => client = client.with_snapshot(snapshot),
+        Ok(None) => {}
+        Err(error) => warn!("Could not load endoflife.date snapshot: {error}"),
+    }
+
+    let mut providers: Vec<Box<dyn LifecycleProvider>> = Vec::default();
+
+    if let Some(lifecycle_file) = config.lifecycle_file() {
+        match FileProvider::load(lifecycle_file) {
+            Ok(provider) => providers.push(Box::new(provider)),
+            Err(error) => warn!(
+                "Could not load lifecycle file '{}': {error}",
+                lifecycle_file.display()
+            ),
+        }
+    }
+
+    providers.push(Box::new(client));
+
+    let resolver = CycleResolver::new(CompositeProvider::new(providers));
+
+    let Some(policy_file) = config.policy_file() else {
+        return resolver;
+    };
+
+    match Policy::load(policy_file) {
+        Ok(policy) => resolver.with_policy(policy),
+        Err(error) => {
+            warn!(
+                "Could not load policy file '{}': {error}",
+                policy_file.display()
+            );
+            resolver
+        }
+    }
 }
 
-async fn print_info<T: Extractor>(
-    extractor: T,
-    program_info: &ProgramInfo,
-) -> Result<(), Box<dyn Error>> {
-    if let Some(version) = extractor.version().await? {
+fn print_explanations(programs: &[Program], resolver: &CycleResolver, options: &DetectionOptions) {
+    let environment = &options.extractor.environment;
+    println!(
+        "Commands run in {} with PATH={} LANG=C LC_ALL=C and no stdin",
+        environment.working_directory.display(),
+        environment.path
+    );
+
+    for explanation in explain(programs, resolver, &options.extractor) {
         println!(
-            "{} ({}) found in Version {}",
-            program_info.title,
-            T::extractor_name(),
-            version.string
+            "{} ({})",
+            explanation.program.info.title, explanation.source
         );
+        for action in explanation.actions {
+            println!("  {action}");
+        }
+    }
+}
+
+fn print_snapshot_date(resolver: &CycleResolver) {
+    if let Some(date) = resolver.provider().snapshot_date() {
+        println!("Release cycles were taken from the endoflife.date snapshot of {date}");
+    }
+}
+
+/// Returns the number of failed detections.
+async fn gather_program_info(
+    programs: &[Program],
+    resolver: &CycleResolver,
+    options: &DetectionOptions,
+) -> usize {
+    let mut errors = 0;
+
+    for program_detection in detect_all(programs, resolver, options).await {
+        if !print_info(&program_detection) {
+            errors += 1;
+        }
+    }
+
+    errors
+}
+
+/// Returns `false` if the detection failed.
+fn print_info(program_detection: &ProgramDetection) -> bool {
+    let ProgramDetection {
+        program,
+        source,
+        ref location,
+        ref detection,
+    } = *program_detection;
+    let program_info = &program.info;
+    let origin = location.as_ref().map_or_else(
+        || source.to_string(),
+        |path| format!("{source}: {}", path.display()),
+    );
+
+    match *detection {
+        Detection::Found { ref version, .. } | Detection::ProviderError { ref version, .. } => {
+            println!(
+                "{} ({}) found in Version {}",
+                program_info.title, origin, version
+            );
+        }
+        Detection::NotInstalled => {}
+        Detection::ExtractorError(ExtractorError::TimedOut(limit)) => {
+            eprintln!(
+                "{} ({}) timed out after {} seconds",
+                program_info.title,
+                origin,
+                limit.as_secs_f32()
+            );
+        }
+        Detection::ExtractorError(ExtractorError::PermissionRequired(ref user)) => {
+            eprintln!(
+                "{} ({}) was not checked: permission required to run as {user}",
+                program_info.title, origin
+            );
+        }
+        Detection::ExtractorError(ref error) => {
+            eprintln!(
+                "{} ({}) could not be detected: {}",
+                program_info.title,
+                origin,
+                error_chain(error)
+            );
+        }
+    }
 
-        if let Some(ref endoflife_date_id) = program_info.endoflife_date_id {
-            const BASE_URL: &str = "https://endoflife.date/api";
-            let client = EndOfLifeDateClient::new(BASE_URL);
-            if let Ok(cycle_info) = client
-                .get_release_cycle(
-                    endoflife_date_id,
-                    endoflife_date::CycleId::String(version.cycle.clone()),
-                )
-                .await
-            {
-                print_end_of_life_info(&version, &cycle_info);
+    match *detection {
+        Detection::Found {
+            ref version,
+            release_cycle: Some(ref cycle_info),
+        } => print_end_of_life_info(version, cycle_info),
+        Detection::Found {
+            ref version,
+            release_cycle: None,
+        } => {
+            if let Some(reference) = program_info.lifecycle_reference() {
+                println!(
+                    "No release cycle of {} matches version {version} (cycle {})",
+                    reference.product, version.cycle
+                );
             }
         }
+        Detection::ProviderError { ref error, .. } => {
+            eprintln!(
+                "Could not look up the release cycle of {}: {}",
+                program_info.title,
+                error_chain(error)
+            );
+        }
+        Detection::NotInstalled | Detection::ExtractorError(_) => {}
     }
-    Ok(())
+
+    !detection.is_error()
 }
 
-fn print_end_of_life_info(version: &Version, cycle_info: &ReleaseCycle) {
+fn print_end_of_life_info(version: &Version, cycle_info: &Cycle) {
+    if cycle_info.overridden {
+        println!(
+            "Release cycle {} is defined by the local policy",
+            cycle_info.cycle
+        );
+    }
+
+    if let Some(Ok(latest)) = cycle_info
+        .latest
+        .as_ref()
+        .map(|latest| Version::parse(latest, &version.cycle, version.scheme))
+    {
+        let releases_behind = version.releases_behind(&latest);
+        if releases_behind > 0 {
+            match cycle_info.latest_release_date {
+                Some(released) => println!(
+                    "Version {version} is {releases_behind} release(s) behind the latest release {latest} ({released})"
+                ),
+                None => println!(
+                    "Version {version} is {releases_behind} release(s) behind the latest release {latest}"
+                ),
+            }
+        }
+    }
+
     if let DateOrBool::Date(eol_date) = cycle_info.eol {
         let today = Utc::now().date_naive();
 
@@ -160,6 +439,19 @@ fn print_end_of_life_info(version: &Version, cycle_info: &ReleaseCycle) {
             );
         }
     }
+
+    if let Some(DateOrBool::Date(extended_date)) = cycle_info.extended_support {
+        let remaining_time = extended_date - Utc::now().date_naive();
+
+        if remaining_time > TimeDelta::days(0) {
+            println!(
+                "Extended support for version {} is available for {} days ({})",
+                version.cycle,
+                remaining_time.num_days(),
+                extended_date
+            );
+        }
+    }
 }
 
 async fn update_database(config: &Config) -> Result<(), Box<dyn Error>> {
@@ -178,7 +470,9 @@ async fn update_database(config: &Config) -> Result<(), Box<dyn Error>> {
         return Err(Box::new(std::io::Error::from(std::io::ErrorKind::NotFound)));
     }
 
-    let new_db = Database::download_update(config.update_url(), database_folder).await?;
+    let new_db =
+        Database::download_update(&http_client(config)?, config.update_url(), database_folder)
+            .await?;
 
-    Database::install_update(&new_db, database_folder).await
+    Ok(Database::install_update(&new_db, database_folder).await?)
 }
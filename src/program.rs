@@ -2,44 +2,324 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use crate::extractor::{binary::BinaryExtractor, docker::DockerExtractor};
-use serde::{Deserialize, Serialize};
+use crate::{
+    extractor::{binary::BinaryExtractor, docker::DockerExtractor},
+    version_scheme::{VersionError, VersionKey, VersionScheme},
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    cmp::Ordering,
+    collections::BTreeMap,
+    fmt::{self, Display},
+    str::FromStr,
+};
 
 #[derive(Deserialize, Serialize, Clone, PartialEq, Eq, Debug)]
 pub struct ProgramInfo {
     pub id: String,
     pub title: String,
     pub endoflife_date_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cycle_template: Option<CycleTemplate>,
+    /// Maps detected cycles to endoflife.date cycles with a different name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cycle_mapping: Option<BTreeMap<String, String>>,
+    /// Takes precedence over `endoflife_date_id`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lifecycle: Option<LifecycleReference>,
+}
+
+impl ProgramInfo {
+    /// The product whose release cycles apply to the program. Falls back to
+    /// `endoflife_date_id`, which is looked up in all providers.
+    #[must_use]
+    pub fn lifecycle_reference(&self) -> Option<LifecycleReference> {
+        self.lifecycle.clone().or_else(|| {
+            self.endoflife_date_id
+                .clone()
+                .map(|product| LifecycleReference {
+                    provider: None,
+                    product,
+                })
+        })
+    }
+}
+
+/// Names a product of a lifecycle provider.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct LifecycleReference {
+    /// All providers are queried in order if missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    pub product: String,
 }
 
 #[derive(Deserialize, Serialize, Clone, PartialEq, Eq, Debug)]
 pub struct Program {
     pub info: ProgramInfo,
+    #[serde(default)]
+    pub version_scheme: VersionScheme,
     pub binary: Option<Vec<BinaryExtractor>>,
     pub docker: Option<DockerExtractor>,
 }
 
+impl Program {
+    #[must_use]
+    pub fn version_format(&self) -> VersionFormat {
+        VersionFormat {
+            scheme: self.version_scheme,
+            cycle_template: self.info.cycle_template.clone(),
+        }
+    }
+}
+
+/// Builds the release cycle of a version from its components, e.g.
+/// `{major}` or `{major}.{minor}`. It is (de)serialized as its template.
 #[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CycleTemplate(String);
+
+impl CycleTemplate {
+    pub const PLACEHOLDERS: [&'static str; 4] = ["major", "minor", "patch", "extra"];
+
+    pub fn new(template: &str) -> Result<Self, VersionError> {
+        let invalid = || VersionError::InvalidCycleTemplate(template.to_string());
+
+        let mut rest = template;
+        while let Some(start) = rest.find(['{', '}']) {
+            let (placeholder, tail) = rest[start..]
+                .strip_prefix('{')
+                .and_then(|placeholder| placeholder.split_once('}'))
+                .ok_or_else(invalid)?;
+            if !Self::PLACEHOLDERS.contains(&placeholder) {
+                return Err(invalid());
+            }
+            rest = tail;
+        }
+
+        Ok(CycleTemplate(template.to_string()))
+    }
+
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Fails if the template uses a component the version does not have.
+    pub fn render(&self, version: &Version) -> Result<String, VersionError> {
+        let mut cycle = self.0.clone();
+
+        for placeholder in Self::PLACEHOLDERS {
+            let pattern = format!("{{{placeholder}}}");
+            if !cycle.contains(&pattern) {
+                continue;
+            }
+
+            let value = match placeholder {
+                "major" => Some(version.major.to_string()),
+                "minor" => version.minor.map(|minor| minor.to_string()),
+                "patch" => version.patch.map(|patch| patch.to_string()),
+                _ => version.extra.clone(),
+            }
+            .ok_or(VersionError::MissingComponent(placeholder))?;
+
+            cycle = cycle.replace(&pattern, &value);
+        }
+
+        Ok(cycle)
+    }
+}
+
+impl Serialize for CycleTemplate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for CycleTemplate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let template = String::deserialize(deserializer)?;
+
+        CycleTemplate::new(&template).map_err(de::Error::custom)
+    }
+}
+
+/// Everything needed to turn an extracted version string into a [`Version`].
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct VersionFormat {
+    pub scheme: VersionScheme,
+    pub cycle_template: Option<CycleTemplate>,
+}
+
+impl VersionFormat {
+    /// Parses `string` with the scheme. The release cycle is `cycle` if it
+    /// was extracted, otherwise it is built from the cycle template, or is
+    /// `major.minor` if there is no template.
+    pub fn parse(&self, string: &str, cycle: Option<&str>) -> Result<Version, VersionError> {
+        if let Some(cycle) = cycle {
+            return Version::parse(string, cycle, self.scheme);
+        }
+
+        match self.cycle_template {
+            Some(ref template) => {
+                let mut version = Version::parse(string, "", self.scheme)?;
+                version.cycle = template.render(&version)?;
+                Ok(version)
+            }
+            None => Version::parse_with_default_cycle(string, self.scheme),
+        }
+    }
+}
+
+impl From<VersionScheme> for VersionFormat {
+    fn from(scheme: VersionScheme) -> Self {
+        VersionFormat {
+            scheme,
+            cycle_template: None,
+        }
+    }
+}
+
+/// A detected program version.
+///
+/// Versions are totally ordered by the `key` their [`VersionScheme`]
+/// derived from `string`. For the default semver scheme, `1.0` and `1.0.0`
+/// compare equal and `1.0.0-rc1` sorts before `1.0.0`.
+///
+/// Only `string`, `cycle` and `scheme` are serialized, all other fields are
+/// parsed again on deserialization.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(try_from = "SerializedVersion", into = "SerializedVersion")]
 pub struct Version {
     pub string: String,
     pub cycle: String,
+    pub scheme: VersionScheme,
     pub major: usize,
     pub minor: Option<usize>,
     pub patch: Option<usize>,
     pub extra: Option<String>,
+    pub key: VersionKey,
 }
 
-impl PartialOrd for Version {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        match self.major.partial_cmp(&other.major) {
-            Some(core::cmp::Ordering::Equal) => {}
-            ord => return ord,
+#[derive(Deserialize, Serialize)]
+struct SerializedVersion {
+    string: String,
+    cycle: String,
+    #[serde(default)]
+    scheme: VersionScheme,
+}
+
+impl TryFrom<SerializedVersion> for Version {
+    type Error = VersionError;
+
+    fn try_from(version: SerializedVersion) -> Result<Self, Self::Error> {
+        Version::parse(&version.string, &version.cycle, version.scheme)
+    }
+}
+
+impl From<Version> for SerializedVersion {
+    fn from(version: Version) -> Self {
+        SerializedVersion {
+            string: version.string,
+            cycle: version.cycle,
+            scheme: version.scheme,
         }
-        match self.minor.partial_cmp(&other.minor) {
-            Some(core::cmp::Ordering::Equal) => {}
-            ord => return ord,
+    }
+}
+
+impl Version {
+    pub fn parse(string: &str, cycle: &str, scheme: VersionScheme) -> Result<Self, VersionError> {
+        let parsed = scheme.parse(string)?;
+
+        Ok(Version {
+            string: string.to_string(),
+            cycle: cycle.to_string(),
+            scheme,
+            major: parsed.major,
+            minor: parsed.minor,
+            patch: parsed.patch,
+            extra: parsed.extra,
+            key: parsed.key,
+        })
+    }
+
+    /// Parses `string` like [`Version::parse`] and uses `major.minor` (or
+    /// only `major`) as the release cycle.
+    pub fn parse_with_default_cycle(
+        string: &str,
+        scheme: VersionScheme,
+    ) -> Result<Self, VersionError> {
+        let mut version = Version::parse(string, "", scheme)?;
+
+        version.cycle = match version.minor {
+            Some(minor) => format!("{}.{minor}", version.major),
+            None => version.major.to_string(),
+        };
+
+        Ok(version)
+    }
+
+    /// Counts the releases between `self` and the newer `latest` version of
+    /// the same cycle.
+    ///
+    /// The count is taken at the first of `major`, `minor` and `patch` that
+    /// differs, so `15.5` is three releases behind `15.8`. Versions that only
+    /// differ in `extra` are one release apart. Returns `0` if `self` is not
+    /// older than `latest`.
+    #[must_use]
+    pub fn releases_behind(&self, latest: &Version) -> usize {
+        if self >= latest {
+            return 0;
         }
-        self.patch.partial_cmp(&other.patch)
+
+        let components = |version: &Version| {
+            [
+                version.major,
+                version.minor.unwrap_or(0),
+                version.patch.unwrap_or(0),
+            ]
+        };
+
+        components(self)
+            .iter()
+            .zip(components(latest).iter())
+            .find(|(installed, latest)| installed != latest)
+            .map_or(1, |(installed, latest)| latest.saturating_sub(*installed))
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(&self.string)
+    }
+}
+
+/// Parses a version with the default [`VersionScheme`], see
+/// [`Version::parse_with_default_cycle`].
+impl FromStr for Version {
+    type Err = VersionError;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        Version::parse_with_default_cycle(string, VersionScheme::default())
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for Version {}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
     }
 }
 
@@ -47,15 +327,22 @@ impl PartialOrd for Version {
 mod tests {
     use super::*;
 
+    fn parse(string: &str) -> Version {
+        Version::parse(string, "", VersionScheme::Semver).expect("Could not parse version")
+    }
+
     fn version(major: usize, minor: Option<usize>, patch: Option<usize>) -> Version {
-        Version {
-            major,
-            minor,
-            patch,
-            string: String::default(),
-            cycle: String::default(),
-            extra: None,
-        }
+        let string = [Some(major), minor, patch]
+            .iter()
+            .flatten()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(".");
+        parse(&string)
+    }
+
+    fn version_with_extra(major: usize, minor: usize, patch: usize, extra: &str) -> Version {
+        parse(&format!("{major}.{minor}.{patch}{extra}"))
     }
 
     #[test]
@@ -88,4 +375,185 @@ mod tests {
         assert!(v111 < version(2, Some(1), None));
         assert!(v111 < version(2, None, None));
     }
+
+    #[test]
+    fn missing_components_are_zero() {
+        assert_eq!(version(1, Some(0), None), version(1, Some(0), Some(0)));
+        assert_eq!(version(1, None, None), version(1, Some(0), Some(0)));
+        assert!(version(1, None, None) < version(1, Some(0), Some(1)));
+    }
+
+    #[test]
+    fn pre_releases() {
+        let release = version(1, Some(0), Some(0));
+        let rc1 = version_with_extra(1, 0, 0, "-rc1");
+        let rc2 = version_with_extra(1, 0, 0, "-rc2");
+        let rc10 = version_with_extra(1, 0, 0, "-rc10");
+
+        assert!(rc1 < release);
+        assert!(rc1 < rc2);
+        assert!(rc2 < rc10);
+        assert!(version(0, Some(9), Some(9)) < rc1);
+
+        assert!(version_with_extra(1, 0, 0, "-alpha") < version_with_extra(1, 0, 0, "-alpha.1"));
+        assert!(version_with_extra(1, 0, 0, "-alpha.1") < version_with_extra(1, 0, 0, "-beta"));
+        assert!(version_with_extra(1, 0, 0, "-beta.2") < version_with_extra(1, 0, 0, "-beta.11"));
+        assert!(version_with_extra(1, 0, 0, "-1") < version_with_extra(1, 0, 0, "-alpha"));
+        assert!(version_with_extra(3, 13, 0, "rc2") < version(3, Some(13), Some(0)));
+    }
+
+    #[test]
+    fn tilde_and_post_releases() {
+        let release = version(2, Some(4), Some(57));
+        let tilde = version_with_extra(2, 4, 57, "~rc1");
+        let tilde_tilde = version_with_extra(2, 4, 57, "~rc1~1");
+        let deb = version_with_extra(2, 4, 57, "+deb12u1");
+        let deb2 = version_with_extra(2, 4, 57, "+deb12u2");
+
+        assert!(tilde < release);
+        assert!(tilde_tilde < tilde);
+        assert!(release < deb);
+        assert!(deb < deb2);
+        assert!(deb < version(2, Some(4), Some(58)));
+    }
+
+    #[test]
+    fn sorting_is_total() {
+        let mut versions = [
+            version(1, Some(0), Some(0)),
+            version_with_extra(1, 0, 0, "+1"),
+            version_with_extra(1, 0, 0, "-rc1"),
+            version(0, Some(9), None),
+            version_with_extra(1, 0, 0, "~rc1"),
+            version_with_extra(1, 0, 0, "-rc01"),
+        ];
+        // `sort` is stable, equal versions keep their relative order.
+        versions.sort();
+
+        let extras: Vec<_> = versions
+            .iter()
+            .map(|version| version.extra.as_deref())
+            .collect();
+        assert_eq!(
+            extras,
+            vec![
+                None,
+                Some("-rc1"),
+                Some("~rc1"),
+                Some("-rc01"),
+                None,
+                Some("+1")
+            ]
+        );
+        assert_eq!(
+            version_with_extra(1, 0, 0, "-rc1"),
+            version_with_extra(1, 0, 0, "-rc01")
+        );
+    }
+
+    #[test]
+    fn releases_behind() {
+        let postgres = version(15, Some(8), None);
+
+        assert_eq!(version(15, Some(5), None).releases_behind(&postgres), 3);
+        assert_eq!(version(15, Some(8), None).releases_behind(&postgres), 0);
+        assert_eq!(version(15, Some(9), None).releases_behind(&postgres), 0);
+
+        let nginx = version(1, Some(26), Some(2));
+        assert_eq!(version(1, Some(26), Some(0)).releases_behind(&nginx), 2);
+        assert_eq!(version(1, Some(25), Some(4)).releases_behind(&nginx), 1);
+        assert_eq!(
+            version_with_extra(1, 26, 2, "-rc1").releases_behind(&nginx),
+            1
+        );
+    }
+
+    #[test]
+    fn display_and_from_str() {
+        let version: Version = "1.18.0-rc1".parse().expect("Could not parse version");
+
+        assert_eq!(version.to_string(), "1.18.0-rc1");
+        assert_eq!(version.cycle, "1.18");
+        assert_eq!(version.scheme, VersionScheme::Semver);
+        assert_eq!(version, version_with_extra(1, 18, 0, "-rc1"));
+
+        assert!("rc1".parse::<Version>().is_err());
+    }
+
+    #[test]
+    fn serialization() {
+        let version = Version::parse("1:2.4.57-2+deb12u1", "2.4", VersionScheme::Debian)
+            .expect("Could not parse version");
+
+        let json = serde_json::to_string(&version).expect("Serialization failed");
+        assert_eq!(
+            json,
+            r#"{"string":"1:2.4.57-2+deb12u1","cycle":"2.4","scheme":"debian"}"#
+        );
+
+        let deserialized: Version = serde_json::from_str(&json).expect("Deserialization failed");
+        assert_eq!(deserialized.string, version.string);
+        assert_eq!(deserialized.cycle, version.cycle);
+        assert_eq!(deserialized.scheme, version.scheme);
+        assert_eq!(deserialized.key, version.key);
+
+        let toml: Version = toml::from_str(
+            r#"
+            string = "24.04"
+            cycle = "24.04"
+            scheme = "calver"
+            "#,
+        )
+        .expect("Deserialization failed");
+        assert_eq!(toml.minor, Some(4));
+
+        assert!(serde_json::from_str::<Version>(r#"{"string":"x","cycle":"x"}"#).is_err());
+    }
+
+    #[test]
+    fn cycle_templates() {
+        let release_candidate = version_with_extra(1, 26, 2, "-rc1");
+
+        let render = |template: &str| {
+            CycleTemplate::new(template)
+                .unwrap()
+                .render(&release_candidate)
+        };
+        assert_eq!(render("{major}").unwrap(), "1");
+        assert_eq!(render("{major}.{minor}").unwrap(), "1.26");
+        assert_eq!(render("v{major}.{minor}.x").unwrap(), "v1.26.x");
+        assert_eq!(render("{extra}").unwrap(), "-rc1");
+        assert!(matches!(
+            CycleTemplate::new("{minor}")
+                .unwrap()
+                .render(&version(1, None, None)),
+            Err(VersionError::MissingComponent("minor"))
+        ));
+
+        assert!(CycleTemplate::new("{major").is_err());
+        assert!(CycleTemplate::new("major}").is_err());
+        assert!(CycleTemplate::new("{build}").is_err());
+        assert!(serde_json::from_str::<CycleTemplate>(r#""{mjaor}""#).is_err());
+    }
+
+    #[test]
+    fn version_formats() {
+        let format = VersionFormat {
+            scheme: VersionScheme::Semver,
+            cycle_template: Some(CycleTemplate::new("{major}").unwrap()),
+        };
+
+        assert_eq!(
+            format.parse("128.0.3", Some("128.0")).unwrap().cycle,
+            "128.0"
+        );
+        assert_eq!(format.parse("128.0.3", None).unwrap().cycle, "128");
+        assert_eq!(
+            VersionFormat::default()
+                .parse("1.26.2", None)
+                .unwrap()
+                .cycle,
+            "1.26"
+        );
+    }
 }
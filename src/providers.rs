@@ -2,4 +2,455 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use std::{
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+use thiserror::Error;
+use tokio::sync::OnceCell;
+
+use crate::{
+    explain::Action,
+    program::{LifecycleReference, Version},
+    version_scheme::split_release,
+};
+
+use log::warn;
+
+use self::{composite::CompositeProvider, endoflife_date::DateOrBool, policy::Policy};
+
+pub mod composite;
 pub mod endoflife_date;
+pub mod file;
+pub mod policy;
+
+/// A release cycle of a product, independent of the provider it came from.
+#[derive(Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Cycle {
+    pub cycle: String,
+    pub release_date: Option<NaiveDate>,
+    pub latest: Option<String>,
+    pub latest_release_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub lts: bool,
+    /// End of active support
+    pub support: Option<DateOrBool>,
+    /// End of security support
+    pub eol: DateOrBool,
+    /// End of paid extended support
+    pub extended_support: Option<DateOrBool>,
+    pub link: Option<String>,
+    /// Set if a local [`policy::Policy`] changed the cycle
+    #[serde(skip)]
+    pub overridden: bool,
+}
+
+pub type CyclesFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<Option<Vec<Cycle>>, ProviderError>> + 'a>>;
+
+/// A source of release cycles, e.g. endoflife.date.
+pub trait LifecycleProvider {
+    /// The name database entries select the provider with.
+    fn name(&self) -> &'static str;
+
+    /// Returns `Ok(None)` if the provider does not know `product`.
+    fn release_cycles<'a>(&'a self, product: &'a str) -> CyclesFuture<'a>;
+
+    /// What [`LifecycleProvider::release_cycles`] would do, without doing it.
+    fn explain(&self, _product: &str) -> Vec<Action> {
+        Vec::default()
+    }
+
+    /// Returns the date of the snapshot if any cycles were taken from one.
+    fn snapshot_date(&self) -> Option<NaiveDate> {
+        None
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ProviderError {
+    #[error("Unknown lifecycle provider {0:?}")]
+    UnknownProvider(String),
+
+    #[error("No lifecycle provider knows the product {0:?}")]
+    UnknownProduct(String),
+
+    #[error("Invalid endoflife.date id {0:?}")]
+    InvalidId(String),
+
+    #[error("No cached or snapshot response for {0} available in offline mode")]
+    Offline(String),
+
+    #[error("Request failed: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    #[error("Invalid response: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("Invalid lifecycle file: {0}")]
+    TomlError(#[from] toml::de::Error),
+
+    #[error("IO Error: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+/// Maps detected versions to the release cycles of a product. The cycle list
+/// of every product is only requested once, even by concurrent detections.
+pub struct CycleResolver {
+    provider: CompositeProvider,
+    policy: Option<Policy>,
+    release_cycles: Mutex<HashMap<LifecycleReference, Arc<OnceCell<Vec<Cycle>>>>>,
+}
+
+impl CycleResolver {
+    #[must_use]
+    pub fn new(provider: CompositeProvider) -> Self {
+        CycleResolver {
+            provider,
+            policy: None,
+            release_cycles: Mutex::default(),
+        }
+    }
+
+    /// Applies `policy` to the cycles of every provider.
+    #[must_use]
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    #[must_use]
+    pub fn provider(&self) -> &CompositeProvider {
+        &self.provider
+    }
+
+    /// Asks the provider named by `reference`, or all providers in order if
+    /// it names none. Returns `Ok(None)` if the product has no cycle matching
+    /// `version`.
+    pub async fn resolve(
+        &self,
+        reference: &LifecycleReference,
+        version: &Version,
+        cycle_mapping: Option<&BTreeMap<String, String>>,
+    ) -> Result<Option<Cycle>, ProviderError> {
+        let cell = self
+            .release_cycles
+            .lock()
+            .unwrap()
+            .entry(reference.clone())
+            .or_default()
+            .clone();
+
+        // Failed requests leave the cell empty, so they are retried later.
+        let release_cycles = cell
+            .get_or_try_init(|| self.request_cycles(reference))
+            .await?;
+
+        Ok(find_release_cycle(release_cycles, version, cycle_mapping).cloned())
+    }
+
+    /// What [`CycleResolver::resolve`] would do for `reference` if its cycles
+    /// were not requested yet.
+    #[must_use]
+    pub fn explain(&self, reference: &LifecycleReference) -> Vec<Action> {
+        match reference.provider {
+            Some(ref name) => match self.provider.get(name) {
+                Some(provider) => provider.explain(&reference.product),
+                None => vec![Action::Skip(
+                    ProviderError::UnknownProvider(name.clone()).to_string(),
+                )],
+            },
+            None => self.provider.explain(&reference.product),
+        }
+    }
+
+    async fn request_cycles(
+        &self,
+        reference: &LifecycleReference,
+    ) -> Result<Vec<Cycle>, ProviderError> {
+        let provider: &dyn LifecycleProvider = match reference.provider {
+            Some(ref name) => self
+                .provider
+                .get(name)
+                .ok_or_else(|| ProviderError::UnknownProvider(name.clone()))?,
+            None => &self.provider,
+        };
+
+        let product = &reference.product;
+        let policy = self
+            .policy
+            .as_ref()
+            .filter(|policy| policy.has_product(product));
+
+        let mut release_cycles = match (provider.release_cycles(product).await, policy) {
+            (Ok(Some(release_cycles)), _) => release_cycles,
+            (Ok(None), Some(_)) => Vec::default(),
+            (Ok(None), None) => return Err(ProviderError::UnknownProduct(product.clone())),
+            (Err(error), Some(_)) => {
+                warn!("Only using the local policy for {product}: {error}");
+                Vec::default()
+            }
+            (Err(error), None) => return Err(error),
+        };
+
+        if let Some(policy) = policy {
+            policy.apply(product, &mut release_cycles);
+        }
+
+        Ok(release_cycles)
+    }
+}
+
+/// Picks the release cycle matching `version`, trying in order
+///
+/// 1. the cycle `cycle_mapping` maps the version's cycle to, or the version's
+///    cycle itself,
+/// 2. the cycle whose numbers equal the leading numbers of the version
+///    (`24.04` for `24.4`, `6` for `6.0.1`), preferring more numbers,
+/// 3. the longest cycle that is a prefix of the version string.
+#[must_use]
+pub fn find_release_cycle<'a>(
+    release_cycles: &'a [Cycle],
+    version: &Version,
+    cycle_mapping: Option<&BTreeMap<String, String>>,
+) -> Option<&'a Cycle> {
+    let wanted = cycle_mapping
+        .and_then(|mapping| mapping.get(&version.cycle))
+        .unwrap_or(&version.cycle);
+
+    let with_id = || {
+        release_cycles
+            .iter()
+            .filter(|release_cycle| !release_cycle.cycle.is_empty())
+    };
+
+    if let Some(release_cycle) = with_id().find(|release_cycle| release_cycle.cycle == *wanted) {
+        return Some(release_cycle);
+    }
+
+    let components = [Some(version.major), version.minor, version.patch]
+        .map(|number| number.map(|number| u64::try_from(number).unwrap_or(u64::MAX)));
+    let numeric_match = with_id()
+        .filter_map(|release_cycle| {
+            let (numbers, rest) = split_release(&release_cycle.cycle);
+            let matches = rest.is_empty()
+                && !numbers.is_empty()
+                && numbers.len() <= components.len()
+                && numbers
+                    .iter()
+                    .zip(components.iter())
+                    .all(|(number, component)| Some(*number) == *component);
+            matches.then_some((numbers.len(), release_cycle))
+        })
+        .max_by_key(|(length, _)| *length);
+
+    if let Some((_, release_cycle)) = numeric_match {
+        return Some(release_cycle);
+    }
+
+    with_id()
+        .filter(|release_cycle| {
+            version
+                .string
+                .strip_prefix(release_cycle.cycle.as_str())
+                .is_some_and(|rest| !rest.starts_with(|next: char| next.is_ascii_alphanumeric()))
+        })
+        .max_by_key(|release_cycle| release_cycle.cycle.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use test_log::test;
+
+    use super::*;
+    use crate::{
+        http::HttpClient, providers::endoflife_date::EndOfLifeDateClient,
+        version_scheme::VersionScheme,
+    };
+
+    fn release_cycles(ids: &[&str]) -> Vec<Cycle> {
+        ids.iter()
+            .map(|id| Cycle {
+                cycle: (*id).to_string(),
+                release_date: None,
+                latest: Some((*id).to_string()),
+                latest_release_date: None,
+                lts: false,
+                support: None,
+                eol: DateOrBool::Bool(false),
+                extended_support: None,
+                link: None,
+                overridden: false,
+            })
+            .collect()
+    }
+
+    fn found(release_cycle: Option<&Cycle>) -> Option<&str> {
+        release_cycle.map(|release_cycle| release_cycle.cycle.as_str())
+    }
+
+    #[test]
+    fn find_release_cycles() {
+        let ubuntu = release_cycles(&["24.10", "24.04", "22.04"]);
+        let calver = Version::parse("24.04", "24.4", VersionScheme::Calver).unwrap();
+        assert_eq!(
+            found(find_release_cycle(&ubuntu, &calver, None)),
+            Some("24.04")
+        );
+
+        let linux = release_cycles(&["6.10", "6.1", "6"]);
+        let kernel = Version::parse("6.1.102", "6.1", VersionScheme::Semver).unwrap();
+        assert_eq!(
+            found(find_release_cycle(&linux, &kernel, None)),
+            Some("6.1")
+        );
+        let old_kernel = Version::parse("6.0.1", "6.0", VersionScheme::Semver).unwrap();
+        assert_eq!(
+            found(find_release_cycle(&linux, &old_kernel, None)),
+            Some("6")
+        );
+
+        let java = release_cycles(&["21", "17", "1.8"]);
+        let jre = Version::parse("1.8.0_402", "1.8.0", VersionScheme::Semver).unwrap();
+        assert_eq!(found(find_release_cycle(&java, &jre, None)), Some("1.8"));
+
+        let prefixed = release_cycles(&["2024.1", "v2"]);
+        let matched = Version::parse("2024.1-beta", "beta", VersionScheme::Semver).unwrap();
+        assert_eq!(
+            found(find_release_cycle(&prefixed, &matched, None)),
+            Some("2024.1")
+        );
+
+        let mapping = BTreeMap::from([("1.8.0".to_string(), "17".to_string())]);
+        assert_eq!(
+            found(find_release_cycle(&java, &jre, Some(&mapping))),
+            Some("17")
+        );
+
+        let unknown = Version::parse("11.0.2", "11", VersionScheme::Semver).unwrap();
+        assert_eq!(found(find_release_cycle(&java, &unknown, None)), None);
+    }
+
+    #[test(tokio::test)]
+    async fn resolver_requests_cycles_once() {
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+
+        let mock = server
+            .mock("GET", "/linux.json")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"[
+{
+    "cycle": "6.10",
+    "releaseDate": "2024-07-14",
+    "eol": "2024-11-14",
+    "latest": "6.10.2",
+    "lts": false
+}]"#,
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let resolver = CycleResolver::new(CompositeProvider::new(vec![Box::new(
+            EndOfLifeDateClient::new(&url, HttpClient::default()),
+        )]));
+        let linux = LifecycleReference {
+            provider: Some("endoflife.date".to_string()),
+            product: "linux".to_string(),
+        };
+
+        let current = Version::parse("6.10.1", "6.10", VersionScheme::Semver).unwrap();
+        let old = Version::parse("5.4.0", "5.4", VersionScheme::Semver).unwrap();
+
+        // Concurrent lookups of the same product share one request.
+        let (release_cycle, _) = tokio::join!(
+            resolver.resolve(&linux, &current, None),
+            resolver.resolve(&linux, &current, None)
+        );
+        let release_cycle = release_cycle.expect("Did not receive valid response");
+        assert_eq!(
+            release_cycle.and_then(|release_cycle| release_cycle.latest),
+            Some("6.10.2".to_string())
+        );
+
+        let no_cycle = resolver
+            .resolve(&linux, &old, None)
+            .await
+            .expect("Did not receive valid response");
+        assert!(no_cycle.is_none());
+
+        let unknown_provider = LifecycleReference {
+            provider: Some("vendor".to_string()),
+            product: "linux".to_string(),
+        };
+        assert!(resolver
+            .resolve(&unknown_provider, &current, None)
+            .await
+            .is_err());
+
+        mock.assert_async().await;
+    }
+
+    #[test(tokio::test)]
+    async fn resolver_applies_policy() {
+        let provider = file::FileProvider::parse(
+            r#"
+            [[products.tool]]
+            cycle = "1"
+            eol = "2024-01-01"
+            "#,
+        )
+        .expect("Invalid lifecycle file");
+        let policy = Policy::parse(
+            r#"
+            [products.tool."1"]
+            eol = "2030-01-01"
+
+            [products.vendor-tool."3"]
+            eol = false
+            latest = "3.2"
+            "#,
+        )
+        .expect("Invalid policy");
+        let resolver = CycleResolver::new(CompositeProvider::new(vec![Box::new(provider)]))
+            .with_policy(policy);
+
+        let reference = |product: &str| LifecycleReference {
+            provider: None,
+            product: product.to_string(),
+        };
+        let version = Version::parse("1.0.0", "1", VersionScheme::Semver).unwrap();
+        let vendor_version = Version::parse("3.1", "3", VersionScheme::Semver).unwrap();
+
+        let tool = resolver
+            .resolve(&reference("tool"), &version, None)
+            .await
+            .unwrap()
+            .expect("No matching cycle");
+        assert!(tool.overridden);
+        assert_eq!(
+            tool.eol,
+            DateOrBool::Date(NaiveDate::from_ymd_opt(2030, 1, 1).unwrap())
+        );
+
+        let vendor_tool = resolver
+            .resolve(&reference("vendor-tool"), &vendor_version, None)
+            .await
+            .unwrap()
+            .expect("No matching cycle");
+        assert_eq!(vendor_tool.latest, Some("3.2".to_string()));
+
+        assert!(resolver
+            .resolve(&reference("unknown"), &version, None)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,123 @@
+// SPDX-FileCopyrightText: 2024 Benedikt Bastin
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use chrono::NaiveDate;
+use log::warn;
+
+use super::{CyclesFuture, LifecycleProvider};
+use crate::explain::Action;
+
+/// Queries its providers in order and returns the cycles of the first one
+/// that knows the product.
+pub struct CompositeProvider {
+    providers: Vec<Box<dyn LifecycleProvider>>,
+}
+
+impl CompositeProvider {
+    #[must_use]
+    pub fn new(providers: Vec<Box<dyn LifecycleProvider>>) -> Self {
+        CompositeProvider { providers }
+    }
+
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&dyn LifecycleProvider> {
+        self.providers
+            .iter()
+            .find(|provider| provider.name() == name)
+            .map(Box::as_ref)
+    }
+}
+
+impl LifecycleProvider for CompositeProvider {
+    fn name(&self) -> &'static str {
+        "composite"
+    }
+
+    /// Only fails if no provider knows the product and at least one failed.
+    fn release_cycles<'a>(&'a self, product: &'a str) -> CyclesFuture<'a> {
+        Box::pin(async move {
+            let mut first_error = None;
+
+            for provider in &self.providers {
+                match provider.release_cycles(product).await {
+                    Ok(Some(release_cycles)) => return Ok(Some(release_cycles)),
+                    Ok(None) => {}
+                    Err(error) => {
+                        warn!(
+                            "Could not retrieve release cycles of {product} from {}: {error}",
+                            provider.name()
+                        );
+                        first_error.get_or_insert(error);
+                    }
+                }
+            }
+
+            first_error.map_or(Ok(None), Err)
+        })
+    }
+
+    /// Lists the actions of all providers, although later ones are only
+    /// asked if the earlier ones do not know the product.
+    fn explain(&self, product: &str) -> Vec<Action> {
+        self.providers
+            .iter()
+            .flat_map(|provider| provider.explain(product))
+            .collect()
+    }
+
+    fn snapshot_date(&self) -> Option<NaiveDate> {
+        self.providers
+            .iter()
+            .find_map(|provider| provider.snapshot_date())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::{file::FileProvider, Cycle};
+
+    #[tokio::test]
+    async fn first_known_product() {
+        let internal = FileProvider::parse(
+            r#"
+            [[products.tool]]
+            cycle = "1"
+            eol = "2030-01-01"
+            "#,
+        )
+        .expect("Invalid lifecycle file");
+        let vendor = FileProvider::parse(
+            r#"
+            [[products.tool]]
+            cycle = "2"
+            eol = false
+
+            [[products.database]]
+            cycle = "16"
+            eol = false
+            "#,
+        )
+        .expect("Invalid lifecycle file");
+        let composite = CompositeProvider::new(vec![Box::new(internal), Box::new(vendor)]);
+
+        let cycle_names = |release_cycles: Option<Vec<Cycle>>| {
+            release_cycles.map(|release_cycles| {
+                release_cycles
+                    .into_iter()
+                    .map(|release_cycle| release_cycle.cycle)
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        let tool = composite.release_cycles("tool").await.unwrap();
+        assert_eq!(cycle_names(tool), Some(vec!["1".to_string()]));
+        let database = composite.release_cycles("database").await.unwrap();
+        assert_eq!(cycle_names(database), Some(vec!["16".to_string()]));
+        assert_eq!(composite.release_cycles("unknown").await.unwrap(), None);
+
+        assert!(composite.get("file").is_some());
+        assert!(composite.get("endoflife.date").is_none());
+    }
+}
@@ -2,11 +2,23 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use chrono::NaiveDate;
-use log::info;
-use serde::{Deserialize, Serialize};
+use log::{info, log, warn, Level};
+use reqwest::StatusCode;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{Cycle, CyclesFuture, LifecycleProvider, ProviderError};
+use crate::{explain::Action, http::HttpClient};
+
+use self::{
+    cache::{CachedResponse, ResponseCache},
+    snapshot::Snapshot,
+};
+
+pub mod cache;
+pub mod snapshot;
 
 #[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
 #[serde(untagged)]
@@ -22,82 +34,305 @@ pub enum Lts {
     String(String),
 }
 
-#[derive(Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy)]
 #[serde(untagged)]
 pub enum DateOrBool {
     Date(NaiveDate),
     Bool(bool),
 }
 
-#[derive(Deserialize)]
+impl CycleId {
+    #[must_use]
+    pub fn to_text(&self) -> String {
+        match self {
+            CycleId::String(string) => string.clone(),
+            CycleId::Number(number) => number.to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct ReleaseCycle {
     pub cycle: Option<CycleId>,
     pub release_date: NaiveDate,
     pub eol: DateOrBool,
     pub latest: String,
+    pub latest_release_date: Option<NaiveDate>,
     pub link: Option<String>,
     pub lts: Lts,
     pub support: Option<DateOrBool>,
+    pub extended_support: Option<DateOrBool>,
     pub discontinued: Option<DateOrBool>,
 }
 
+/// Whether `id` is a valid product or cycle id, which is used as a segment
+/// of URLs and file paths: it consists of `[a-z0-9._-]` and has no `..`.
+#[must_use]
+pub fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && !id.contains("..")
+        && id
+            .chars()
+            .all(|next| matches!(next, 'a'..='z' | '0'..='9' | '.' | '_' | '-'))
+}
+
+/// Whether every segment of the API path `path` is a valid id, so it stays
+/// inside the cache and snapshot directories.
+fn is_valid_path(path: &str) -> bool {
+    path.split('/').all(is_valid_id)
+}
+
+/// The public endoflife.date API
+pub const DEFAULT_BASE_URL: &str = "https://endoflife.date/api";
+
 pub struct EndOfLifeDateClient {
     base_url: String,
+    http: HttpClient,
+    cache: Option<ResponseCache>,
+    snapshot: Option<Snapshot>,
+    snapshot_used: AtomicBool,
+    offline: bool,
 }
 
 impl EndOfLifeDateClient {
+    /// Requests are sent with `http`, which carries the proxy, CA bundle,
+    /// timeout and retries of the configuration.
     #[must_use]
-    pub fn new(base_url: &str) -> Self {
+    pub fn new(base_url: &str, http: HttpClient) -> Self {
         EndOfLifeDateClient {
             base_url: base_url.to_owned(),
+            http,
+            cache: None,
+            snapshot: None,
+            snapshot_used: AtomicBool::default(),
+            offline: false,
         }
     }
 
+    /// Answers requests from `cache` while its entries are fresh and falls
+    /// back to stale entries if endoflife.date cannot be reached.
+    #[must_use]
+    pub fn with_cache(mut self, cache: ResponseCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Answers requests from `snapshot` if neither endoflife.date nor the
+    /// cache can.
+    #[must_use]
+    pub fn with_snapshot(mut self, snapshot: Snapshot) -> Self {
+        self.snapshot = Some(snapshot);
+        self
+    }
+
+    /// Only answers requests from the cache or the snapshot, regardless of
+    /// the age of their entries.
+    #[must_use]
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
     pub async fn get_release_cycles(
         &self,
         product: &str,
-    ) -> Result<Vec<ReleaseCycle>, Box<dyn Error>> {
-        let url = format!("{}/{product}.json", self.base_url);
-        info!("Retrieving ReleaseCycles for {product} from {url}");
-
-        let response = reqwest::get(url).await?;
-        info!("Recieved response {}", response.status());
+    ) -> Result<Vec<ReleaseCycle>, ProviderError> {
+        info!("Retrieving ReleaseCycles for {product}");
+        if !is_valid_id(product) {
+            return Err(ProviderError::InvalidId(product.to_string()));
+        }
 
-        Ok(response.json::<Vec<ReleaseCycle>>().await?)
+        self.get(&format!("{product}.json")).await
     }
 
     pub async fn get_release_cycle(
         &self,
         product: &str,
         cycle: CycleId,
-    ) -> Result<ReleaseCycle, Box<dyn Error>> {
-        let cycle_text = match cycle {
-            CycleId::String(string) => string,
-            CycleId::Number(number) => number.to_string(),
+    ) -> Result<ReleaseCycle, ProviderError> {
+        let cycle_text = cycle.to_text();
+        info!("Retrieving ReleaseCycle {cycle_text} for {product}");
+        for id in [product, &cycle_text] {
+            if !is_valid_id(id) {
+                return Err(ProviderError::InvalidId(id.to_string()));
+            }
+        }
+
+        self.get(&format!("{product}/{cycle_text}.json")).await
+    }
+
+    pub async fn get_all_products(&self) -> Result<Vec<String>, ProviderError> {
+        info!("Retrieving supported products");
+
+        self.get("all.json").await
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, ProviderError> {
+        let cached = match self.cache {
+            Some(ref cache) => cache.load(path).await,
+            None => None,
         };
-        let url = format!("{}/{product}/{cycle_text}.json", self.base_url);
-        info!("Retrieving ReleaseCycle {cycle_text} for {product} from {url}");
 
-        let response = reqwest::get(url).await?;
-        info!("Recieved response {}", response.status());
+        let stale = match cached {
+            Some(CachedResponse::Fresh(body)) => match serde_json::from_str(&body) {
+                Ok(response) => {
+                    info!("Using cached response for {path}");
+                    return Ok(response);
+                }
+                Err(error) => {
+                    warn!("Ignoring invalid cached response for {path}: {error}");
+                    None
+                }
+            },
+            Some(CachedResponse::Stale(body)) => Some(body),
+            None => None,
+        };
 
-        Ok(response.json::<ReleaseCycle>().await?)
+        let error = if self.offline {
+            ProviderError::Offline(path.to_string())
+        } else {
+            match self.fetch(path).await {
+                Ok(body) => {
+                    let response = serde_json::from_str(&body)?;
+                    if let Some(ref cache) = self.cache {
+                        if let Err(error) = cache.store(path, &body).await {
+                            warn!("Could not cache response for {path}: {error}");
+                        }
+                    }
+                    return Ok(response);
+                }
+                Err(error) => error,
+            }
+        };
+
+        // Falling back is expected in offline mode, but worth a warning otherwise.
+        let level = if self.offline {
+            Level::Info
+        } else {
+            Level::Warn
+        };
+
+        if let Some(body) = stale {
+            match serde_json::from_str(&body) {
+                Ok(response) => {
+                    log!(level, "Using stale cached response for {path} ({error})");
+                    return Ok(response);
+                }
+                Err(json_error) => {
+                    warn!("Ignoring invalid cached response for {path}: {json_error}");
+                }
+            }
+        }
+
+        if let Some(ref snapshot) = self.snapshot {
+            if let Some(body) = snapshot.load_response(path).await {
+                log!(
+                    level,
+                    "Using response for {path} from snapshot of {} ({error})",
+                    snapshot.date()
+                );
+                self.snapshot_used.store(true, Ordering::Relaxed);
+                return Ok(serde_json::from_str(&body)?);
+            }
+        }
+
+        Err(error)
     }
 
-    pub async fn get_all_products(&self) -> Result<Vec<String>, Box<dyn Error>> {
-        let url = format!("{}/all.json", self.base_url);
-        info!("Retrieving supported products from {url}");
+    async fn fetch(&self, path: &str) -> Result<String, ProviderError> {
+        let url = format!("{}/{path}", self.base_url);
+        info!("Requesting {url}");
 
-        let response = reqwest::get(url).await?;
-        info!("Recieved response {}", response.status());
+        Ok(self.http.get(&url).await?.text().await?)
+    }
+}
 
-        Ok(response.json::<Vec<String>>().await?)
+impl From<ReleaseCycle> for Cycle {
+    fn from(release_cycle: ReleaseCycle) -> Self {
+        Cycle {
+            cycle: release_cycle
+                .cycle
+                .as_ref()
+                .map(CycleId::to_text)
+                .unwrap_or_default(),
+            release_date: Some(release_cycle.release_date),
+            latest: Some(release_cycle.latest),
+            latest_release_date: release_cycle.latest_release_date,
+            lts: release_cycle.lts != Lts::Bool(false),
+            support: release_cycle.support,
+            eol: release_cycle.eol,
+            extended_support: release_cycle.extended_support,
+            link: release_cycle.link,
+            overridden: false,
+        }
+    }
+}
+
+impl LifecycleProvider for EndOfLifeDateClient {
+    fn name(&self) -> &'static str {
+        "endoflife.date"
+    }
+
+    /// Unknown products are answered with 404 Not Found.
+    fn release_cycles<'a>(&'a self, product: &'a str) -> CyclesFuture<'a> {
+        Box::pin(async move {
+            match self.get_release_cycles(product).await {
+                Ok(release_cycles) => {
+                    Ok(Some(release_cycles.into_iter().map(Cycle::from).collect()))
+                }
+                Err(ProviderError::HttpError(ref error))
+                    if error.status() == Some(StatusCode::NOT_FOUND) =>
+                {
+                    Ok(None)
+                }
+                Err(error) => Err(error),
+            }
+        })
+    }
+
+    /// Follows [`EndOfLifeDateClient::get`]: fresh cache entries, requests,
+    /// stale cache entries and the snapshot.
+    fn explain(&self, product: &str) -> Vec<Action> {
+        if !is_valid_id(product) {
+            return vec![Action::Skip(format!(
+                "{product:?} is not a valid endoflife.date id"
+            ))];
+        }
+
+        let path = format!("{product}.json");
+        let mut actions = Vec::default();
+
+        if let Some(ref cache) = self.cache {
+            actions.extend(cache.file_path(&path).map(Action::ReadFile));
+        }
+
+        if !self.offline {
+            actions.push(Action::HttpRequest(format!("GET {}/{path}", self.base_url)));
+            if let Some(ref cache) = self.cache {
+                actions.extend(cache.file_path(&path).map(Action::WriteFile));
+            }
+        }
+
+        if let Some(ref snapshot) = self.snapshot {
+            actions.extend(snapshot.file_path(&path).map(Action::ReadFile));
+        }
+
+        actions
+    }
+
+    fn snapshot_date(&self) -> Option<NaiveDate> {
+        self.snapshot
+            .as_ref()
+            .filter(|_| self.snapshot_used.load(Ordering::Relaxed))
+            .map(Snapshot::date)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use test_log::test;
 
     use super::*;
@@ -111,6 +346,8 @@ mod tests {
             "support": "2022-01-01",
             "eol": "2022-01-01",
             "latest": "21.04",
+            "latestReleaseDate": "2021-04-22",
+            "extendedSupport": "2031-04-22",
             "link": "https://wiki.ubuntu.com/HirsuteHippo/ReleaseNotes/"
         }"#;
 
@@ -133,6 +370,16 @@ mod tests {
             DateOrBool::Date(NaiveDate::from_ymd_opt(2022, 1, 1).unwrap())
         );
         assert_eq!(rc.latest, "21.04");
+        assert_eq!(
+            rc.latest_release_date,
+            Some(NaiveDate::from_ymd_opt(2021, 4, 22).unwrap())
+        );
+        assert_eq!(
+            rc.extended_support,
+            Some(DateOrBool::Date(
+                NaiveDate::from_ymd_opt(2031, 4, 22).unwrap()
+            ))
+        );
         assert_eq!(
             rc.link,
             Some("https://wiki.ubuntu.com/HirsuteHippo/ReleaseNotes/".to_string())
@@ -163,7 +410,7 @@ mod tests {
             .create_async()
             .await;
 
-        let client = EndOfLifeDateClient::new(&url);
+        let client = EndOfLifeDateClient::new(&url, HttpClient::default());
 
         let rcs = client
             .get_release_cycles("linux")
@@ -196,7 +443,7 @@ mod tests {
             .create_async()
             .await;
 
-        let client = EndOfLifeDateClient::new(&url);
+        let client = EndOfLifeDateClient::new(&url, HttpClient::default());
 
         let cycle = CycleId::String("6.10".to_string());
         let rcs = client
@@ -226,7 +473,7 @@ mod tests {
             .create_async()
             .await;
 
-        let client = EndOfLifeDateClient::new(&url);
+        let client = EndOfLifeDateClient::new(&url, HttpClient::default());
 
         let products = client
             .get_all_products()
@@ -237,4 +484,220 @@ mod tests {
 
         mock.assert_async().await;
     }
+
+    const LINUX_CYCLES: &str = r#"[
+{
+    "cycle": "6.10",
+    "releaseDate": "2024-07-14",
+    "eol": "2024-11-14",
+    "latest": "6.10.2",
+    "lts": false
+}]"#;
+
+    #[test(tokio::test)]
+    async fn cached_responses() {
+        let tmp_dir = tempfile::TempDir::new().expect("Could not create tmpdir");
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+
+        let mock = server
+            .mock("GET", "/linux.json")
+            .with_status(200)
+            .with_body(LINUX_CYCLES)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let cache = ResponseCache::new(tmp_dir.path().to_path_buf(), Duration::from_hours(1));
+        let client = EndOfLifeDateClient::new(&url, HttpClient::default()).with_cache(cache);
+
+        for _ in 0..2 {
+            let rcs = client
+                .get_release_cycles("linux")
+                .await
+                .expect("Did not receive valid response");
+            assert_eq!(rcs.len(), 1);
+        }
+        assert!(tmp_dir.path().join("linux.json").is_file());
+
+        mock.assert_async().await;
+    }
+
+    #[test(tokio::test)]
+    async fn invalid_cached_responses_are_refetched() {
+        let tmp_dir = tempfile::TempDir::new().expect("Could not create tmpdir");
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+
+        let mock = server
+            .mock("GET", "/linux.json")
+            .with_status(200)
+            .with_body(LINUX_CYCLES)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let cache = ResponseCache::new(tmp_dir.path().to_path_buf(), Duration::from_hours(1));
+        cache
+            .store("linux.json", "[{")
+            .await
+            .expect("Could not store response");
+        let client = EndOfLifeDateClient::new(&url, HttpClient::default()).with_cache(cache);
+
+        let rcs = client
+            .get_release_cycles("linux")
+            .await
+            .expect("Invalid cached response was not refetched");
+        assert_eq!(rcs.len(), 1);
+        assert_eq!(
+            std::fs::read_to_string(tmp_dir.path().join("linux.json")).unwrap(),
+            LINUX_CYCLES
+        );
+
+        mock.assert_async().await;
+    }
+
+    #[test(tokio::test)]
+    async fn invalid_ids() {
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+
+        let mock = server
+            .mock("GET", mockito::Matcher::Any)
+            .expect(0)
+            .create_async()
+            .await;
+
+        let client = EndOfLifeDateClient::new(&url, HttpClient::default());
+        for product in [
+            "",
+            "..",
+            "../etc/passwd",
+            "/etc/passwd",
+            "Linux",
+            "linux/6.10",
+        ] {
+            assert!(matches!(
+                client.get_release_cycles(product).await,
+                Err(ProviderError::InvalidId(_))
+            ));
+        }
+        assert!(matches!(
+            client
+                .get_release_cycle("linux", CycleId::String("../6.10".to_string()))
+                .await,
+            Err(ProviderError::InvalidId(_))
+        ));
+
+        assert!(is_valid_id("eclipse-temurin"));
+        assert!(is_valid_id("6.10"));
+
+        mock.assert_async().await;
+    }
+
+    #[test(tokio::test)]
+    async fn stale_responses_on_error() {
+        let tmp_dir = tempfile::TempDir::new().expect("Could not create tmpdir");
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+
+        let mock = server
+            .mock("GET", "/linux.json")
+            .with_status(500)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let cache = ResponseCache::new(tmp_dir.path().to_path_buf(), Duration::ZERO);
+        let uncached_client = EndOfLifeDateClient::new(&url, HttpClient::default());
+        assert!(uncached_client.get_release_cycles("linux").await.is_err());
+
+        cache
+            .store("linux.json", LINUX_CYCLES)
+            .await
+            .expect("Could not store response");
+        let client = EndOfLifeDateClient::new(&url, HttpClient::default()).with_cache(cache);
+        let rcs = client
+            .get_release_cycles("linux")
+            .await
+            .expect("Stale response was not used");
+        assert_eq!(rcs.len(), 1);
+
+        mock.assert_async().await;
+    }
+
+    #[test(tokio::test)]
+    async fn offline_mode() {
+        let tmp_dir = tempfile::TempDir::new().expect("Could not create tmpdir");
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+
+        let mock = server
+            .mock("GET", mockito::Matcher::Any)
+            .expect(0)
+            .create_async()
+            .await;
+
+        let cache = ResponseCache::new(tmp_dir.path().to_path_buf(), Duration::ZERO);
+        cache
+            .store("linux.json", LINUX_CYCLES)
+            .await
+            .expect("Could not store response");
+        let client = EndOfLifeDateClient::new(&url, HttpClient::default())
+            .with_cache(cache)
+            .offline(true);
+
+        assert!(client.get_release_cycles("linux").await.is_ok());
+        assert!(client.get_release_cycles("nginx").await.is_err());
+
+        mock.assert_async().await;
+    }
+
+    #[test(tokio::test)]
+    async fn snapshot_responses() {
+        let tmp_dir = tempfile::TempDir::new().expect("Could not create tmpdir");
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+
+        let mock = server
+            .mock("GET", "/linux.json")
+            .with_status(500)
+            .expect(1)
+            .create_async()
+            .await;
+
+        std::fs::write(
+            tmp_dir.path().join("snapshot.json"),
+            r#"{"date": "2024-08-10"}"#,
+        )
+        .expect("Could not write snapshot info");
+        std::fs::write(tmp_dir.path().join("linux.json"), LINUX_CYCLES)
+            .expect("Could not write snapshot");
+        let snapshot = || {
+            Snapshot::load(tmp_dir.path())
+                .expect("Could not load snapshot")
+                .expect("Snapshot not found")
+        };
+
+        let client =
+            EndOfLifeDateClient::new(&url, HttpClient::default()).with_snapshot(snapshot());
+        assert_eq!(client.snapshot_date(), None);
+        let rcs = client
+            .get_release_cycles("linux")
+            .await
+            .expect("Snapshot was not used");
+        assert_eq!(rcs.len(), 1);
+        assert_eq!(
+            client.snapshot_date(),
+            Some(NaiveDate::from_ymd_opt(2024, 8, 10).unwrap())
+        );
+
+        let offline_client = EndOfLifeDateClient::new(&url, HttpClient::default())
+            .with_snapshot(snapshot())
+            .offline(true);
+        assert!(offline_client.get_release_cycles("linux").await.is_ok());
+        assert!(offline_client.get_release_cycles("nginx").await.is_err());
+
+        mock.assert_async().await;
+    }
 }
@@ -0,0 +1,120 @@
+// SPDX-FileCopyrightText: 2024 Benedikt Bastin
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::{
+    io,
+    path::PathBuf,
+    process,
+    time::{Duration, SystemTime},
+};
+
+use tokio::fs;
+
+use super::is_valid_path;
+
+/// Stores the raw bodies of endoflife.date responses on disk, one file per
+/// API path (`linux.json`, `linux/6.10.json`, ...).
+pub struct ResponseCache {
+    directory: PathBuf,
+    ttl: Duration,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum CachedResponse {
+    /// Younger than the TTL
+    Fresh(String),
+    /// Older than the TTL, only used when endoflife.date cannot be reached
+    Stale(String),
+}
+
+impl ResponseCache {
+    #[must_use]
+    pub fn new(directory: PathBuf, ttl: Duration) -> Self {
+        ResponseCache { directory, ttl }
+    }
+
+    /// Returns `None` if `path` is no valid API path, which could lead
+    /// outside of the cache directory.
+    #[must_use]
+    pub fn file_path(&self, path: &str) -> Option<PathBuf> {
+        is_valid_path(path).then(|| self.directory.join(path))
+    }
+
+    pub async fn load(&self, path: &str) -> Option<CachedResponse> {
+        let file_path = self.file_path(path)?;
+        let body = fs::read_to_string(&file_path).await.ok()?;
+        let modified = fs::metadata(&file_path).await.ok()?.modified().ok()?;
+        let age = SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default();
+
+        if age < self.ttl {
+            Some(CachedResponse::Fresh(body))
+        } else {
+            Some(CachedResponse::Stale(body))
+        }
+    }
+
+    /// Replaces the entry atomically, concurrent readers see either the old
+    /// or the new response.
+    pub async fn store(&self, path: &str, body: &str) -> io::Result<()> {
+        let file_path = self.file_path(path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid path {path:?}"),
+            )
+        })?;
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        let mut temporary_name = file_path.clone().into_os_string();
+        temporary_name.push(format!(".{}.tmp", process::id()));
+        fs::write(&temporary_name, body).await?;
+        fs::rename(temporary_name, file_path).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn fresh_and_stale_responses() {
+        let tmp_dir = TempDir::new().expect("Could not create tmpdir");
+
+        let cache = ResponseCache::new(tmp_dir.path().to_path_buf(), Duration::from_hours(1));
+        assert_eq!(cache.load("linux/6.10.json").await, None);
+
+        cache
+            .store("linux/6.10.json", "{}")
+            .await
+            .expect("Could not store response");
+        assert_eq!(
+            cache.load("linux/6.10.json").await,
+            Some(CachedResponse::Fresh("{}".to_string()))
+        );
+
+        let expired = ResponseCache::new(tmp_dir.path().to_path_buf(), Duration::ZERO);
+        assert_eq!(
+            expired.load("linux/6.10.json").await,
+            Some(CachedResponse::Stale("{}".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn paths_stay_inside_the_cache() {
+        let tmp_dir = TempDir::new().expect("Could not create tmpdir");
+        let cache = ResponseCache::new(tmp_dir.path().join("cache"), Duration::from_hours(1));
+
+        for path in ["../linux.json", "/tmp/linux.json", "linux/../../linux.json"] {
+            assert_eq!(cache.file_path(path), None);
+            assert!(cache.store(path, "{}").await.is_err());
+            assert_eq!(cache.load(path).await, None);
+        }
+        assert!(!tmp_dir.path().join("linux.json").exists());
+    }
+}
@@ -0,0 +1,106 @@
+// SPDX-FileCopyrightText: 2024 Benedikt Bastin
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use super::is_valid_path;
+use crate::providers::ProviderError;
+
+/// Folder of the snapshot inside the database folder.
+pub const SNAPSHOT_FOLDER: &str = "endoflife.date";
+
+const SNAPSHOT_INFO: &str = "snapshot.json";
+
+#[derive(Deserialize, Serialize)]
+struct SnapshotInfo {
+    date: NaiveDate,
+}
+
+/// Release cycles of all products referenced in the database, taken from
+/// endoflife.date when the database was built. The responses are stored like
+/// in the [`super::cache::ResponseCache`] (`linux.json`, ...), next to a
+/// `snapshot.json` with the date of the snapshot.
+pub struct Snapshot {
+    directory: PathBuf,
+    date: NaiveDate,
+}
+
+impl Snapshot {
+    /// Returns `Ok(None)` if the database contains no snapshot.
+    pub fn load(directory: &Path) -> Result<Option<Snapshot>, ProviderError> {
+        let info_path = directory.join(SNAPSHOT_INFO);
+        if !info_path.is_file() {
+            return Ok(None);
+        }
+
+        let reader = BufReader::new(File::open(info_path)?);
+        let info: SnapshotInfo = serde_json::from_reader(reader)?;
+
+        Ok(Some(Snapshot {
+            directory: directory.to_path_buf(),
+            date: info.date,
+        }))
+    }
+
+    #[must_use]
+    pub fn date(&self) -> NaiveDate {
+        self.date
+    }
+
+    /// Returns `None` if `path` is no valid API path, like
+    /// [`super::cache::ResponseCache::file_path`].
+    #[must_use]
+    pub fn file_path(&self, path: &str) -> Option<PathBuf> {
+        is_valid_path(path).then(|| self.directory.join(path))
+    }
+
+    pub async fn load_response(&self, path: &str) -> Option<String> {
+        fs::read_to_string(self.file_path(path)?).await.ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn load_snapshot() {
+        let tmp_dir = TempDir::new().expect("Could not create tmpdir");
+        assert!(Snapshot::load(tmp_dir.path())
+            .expect("Could not load snapshot")
+            .is_none());
+
+        std::fs::write(
+            tmp_dir.path().join(SNAPSHOT_INFO),
+            r#"{"date": "2024-08-10"}"#,
+        )
+        .expect("Could not write snapshot info");
+        std::fs::write(tmp_dir.path().join("linux.json"), "[]").expect("Could not write snapshot");
+
+        let snapshot = Snapshot::load(tmp_dir.path())
+            .expect("Could not load snapshot")
+            .expect("Snapshot not found");
+        assert_eq!(
+            snapshot.date(),
+            NaiveDate::from_ymd_opt(2024, 8, 10).unwrap()
+        );
+        assert_eq!(
+            snapshot.load_response("linux.json").await,
+            Some("[]".to_string())
+        );
+        assert_eq!(snapshot.load_response("nginx.json").await, None);
+        assert_eq!(snapshot.load_response("../snapshot.json").await, None);
+        assert_eq!(snapshot.file_path("/etc/passwd"), None);
+    }
+}
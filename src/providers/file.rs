@@ -0,0 +1,98 @@
+// SPDX-FileCopyrightText: 2024 Benedikt Bastin
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::Deserialize;
+
+use super::{Cycle, CyclesFuture, LifecycleProvider, ProviderError};
+
+#[derive(Deserialize)]
+struct LifecycleFile {
+    #[serde(default)]
+    products: BTreeMap<String, Vec<Cycle>>,
+}
+
+/// Release cycles from a local TOML file, e.g. for in-house software. Dates
+/// are written as strings.
+///
+/// ```toml
+/// [[products.internal-tool]]
+/// cycle = "2"
+/// release_date = "2024-01-15"
+/// support = "2025-01-31"
+/// eol = "2026-01-31"
+/// latest = "2.4.1"
+/// ```
+pub struct FileProvider {
+    products: BTreeMap<String, Vec<Cycle>>,
+}
+
+impl FileProvider {
+    pub fn load(path: &Path) -> Result<FileProvider, ProviderError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(content: &str) -> Result<FileProvider, ProviderError> {
+        let file: LifecycleFile = toml::from_str(content)?;
+
+        Ok(FileProvider {
+            products: file.products,
+        })
+    }
+}
+
+impl LifecycleProvider for FileProvider {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    fn release_cycles<'a>(&'a self, product: &'a str) -> CyclesFuture<'a> {
+        Box::pin(async move { Ok(self.products.get(product).cloned()) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::providers::endoflife_date::DateOrBool;
+
+    #[tokio::test]
+    async fn parse_lifecycle_file() {
+        let provider = FileProvider::parse(
+            r#"
+            [[products.internal-tool]]
+            cycle = "2"
+            release_date = "2024-01-15"
+            support = "2025-01-31"
+            eol = "2026-01-31"
+            latest = "2.4.1"
+
+            [[products.internal-tool]]
+            cycle = "1"
+            eol = true
+            "#,
+        )
+        .expect("Invalid lifecycle file");
+
+        let release_cycles = provider
+            .release_cycles("internal-tool")
+            .await
+            .unwrap()
+            .expect("Product not found");
+        assert_eq!(release_cycles.len(), 2);
+        assert_eq!(release_cycles[0].latest, Some("2.4.1".to_string()));
+        assert_eq!(
+            release_cycles[0].eol,
+            DateOrBool::Date(NaiveDate::from_ymd_opt(2026, 1, 31).unwrap())
+        );
+        assert_eq!(release_cycles[1].eol, DateOrBool::Bool(true));
+        assert_eq!(release_cycles[1].support, None);
+
+        assert_eq!(provider.release_cycles("other").await.unwrap(), None);
+        assert!(FileProvider::parse("[[products.tool]]\ncycle = \"1\"").is_err());
+    }
+}
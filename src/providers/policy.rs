@@ -0,0 +1,168 @@
+// SPDX-FileCopyrightText: 2024 Benedikt Bastin
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::Deserialize;
+
+use super::{endoflife_date::DateOrBool, Cycle, ProviderError};
+
+/// Fields of a release cycle that a policy replaces. Unset fields keep the
+/// value of the provider.
+#[derive(Deserialize, Clone, PartialEq, Eq, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct CycleOverride {
+    pub support: Option<DateOrBool>,
+    pub eol: Option<DateOrBool>,
+    pub extended_support: Option<DateOrBool>,
+    pub latest: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PolicyFile {
+    #[serde(default)]
+    products: BTreeMap<String, BTreeMap<String, CycleOverride>>,
+}
+
+/// Local support policy, e.g. support contracts with a vendor, that takes
+/// precedence over the data of the lifecycle providers.
+///
+/// ```toml
+/// [products.postgresql."12"]
+/// eol = "2029-11-14"
+/// extended_support = "2031-11-14"
+/// ```
+pub struct Policy {
+    products: BTreeMap<String, BTreeMap<String, CycleOverride>>,
+}
+
+impl Policy {
+    pub fn load(path: &Path) -> Result<Policy, ProviderError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(content: &str) -> Result<Policy, ProviderError> {
+        let file: PolicyFile = toml::from_str(content)?;
+
+        Ok(Policy {
+            products: file.products,
+        })
+    }
+
+    #[must_use]
+    pub fn has_product(&self, product: &str) -> bool {
+        self.products.contains_key(product)
+    }
+
+    /// Overrides the cycles of `product` named in the policy and adds the
+    /// ones the provider does not know.
+    pub fn apply(&self, product: &str, release_cycles: &mut Vec<Cycle>) {
+        let Some(overrides) = self.products.get(product) else {
+            return;
+        };
+
+        for (name, cycle_override) in overrides {
+            let position = release_cycles
+                .iter()
+                .position(|release_cycle| release_cycle.cycle == *name);
+
+            let release_cycle = if let Some(position) = position {
+                &mut release_cycles[position]
+            } else {
+                release_cycles.push(Cycle {
+                    cycle: name.clone(),
+                    release_date: None,
+                    latest: None,
+                    latest_release_date: None,
+                    lts: false,
+                    support: None,
+                    eol: DateOrBool::Bool(false),
+                    extended_support: None,
+                    link: None,
+                    overridden: false,
+                });
+                release_cycles.last_mut().unwrap()
+            };
+
+            if let Some(support) = cycle_override.support {
+                release_cycle.support = Some(support);
+            }
+            if let Some(eol) = cycle_override.eol {
+                release_cycle.eol = eol;
+            }
+            if let Some(extended_support) = cycle_override.extended_support {
+                release_cycle.extended_support = Some(extended_support);
+            }
+            if let Some(ref latest) = cycle_override.latest {
+                release_cycle.latest = Some(latest.clone());
+            }
+            release_cycle.overridden = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::providers::file::FileProvider;
+    use crate::providers::LifecycleProvider;
+
+    #[tokio::test]
+    async fn override_and_define_cycles() {
+        let provider = FileProvider::parse(
+            r#"
+            [[products.postgresql]]
+            cycle = "16"
+            eol = "2028-11-09"
+            latest = "16.4"
+
+            [[products.postgresql]]
+            cycle = "12"
+            eol = "2024-11-14"
+            latest = "12.20"
+            "#,
+        )
+        .expect("Invalid lifecycle file");
+        let policy = Policy::parse(
+            r#"
+            [products.postgresql."12"]
+            eol = "2029-11-14"
+            extended_support = true
+
+            [products.postgresql."9.6"]
+            eol = "2026-01-01"
+            "#,
+        )
+        .expect("Invalid policy");
+
+        let mut release_cycles = provider
+            .release_cycles("postgresql")
+            .await
+            .unwrap()
+            .unwrap();
+        policy.apply("postgresql", &mut release_cycles);
+
+        assert_eq!(release_cycles.len(), 3);
+        assert!(!release_cycles[0].overridden);
+
+        let overridden = &release_cycles[1];
+        assert!(overridden.overridden);
+        assert_eq!(
+            overridden.eol,
+            DateOrBool::Date(NaiveDate::from_ymd_opt(2029, 11, 14).unwrap())
+        );
+        assert_eq!(overridden.extended_support, Some(DateOrBool::Bool(true)));
+        assert_eq!(overridden.latest, Some("12.20".to_string()));
+
+        let defined = &release_cycles[2];
+        assert_eq!(defined.cycle, "9.6");
+        assert!(defined.overridden);
+
+        assert!(policy.has_product("postgresql"));
+        assert!(!policy.has_product("mariadb"));
+        assert!(Policy::parse("[products.postgresql.\"12\"]\neol_date = true").is_err());
+    }
+}
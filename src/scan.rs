@@ -4,19 +4,29 @@
 
 use std::{
     error::Error,
-    fs,
+    fs, io,
     path::{Path, PathBuf},
 };
 
 use sha256::try_digest;
+use thiserror::Error;
 
 use crate::hash_database::{HashDatabase, VersionedProgramInfo};
 
-fn calculate_hash(file: &Path) -> Result<String, Box<dyn Error>> {
+#[derive(Error, Debug)]
+pub enum ScanError {
+    #[error("IO Error: {0}")]
+    IoError(#[from] io::Error),
+
+    #[error("Hash database lookup failed: {0}")]
+    HashDatabaseError(#[source] Box<dyn Error + Send + Sync>),
+}
+
+fn calculate_hash(file: &Path) -> Result<String, ScanError> {
     Ok(try_digest(file)?)
 }
 
-fn scan_folder(folder: &Path) -> Result<Vec<(PathBuf, String)>, Box<dyn Error>> {
+fn scan_folder(folder: &Path) -> Result<Vec<(PathBuf, String)>, ScanError> {
     let mut file_hashes = Vec::default();
     for entry in fs::read_dir(folder)? {
         let entry = entry?;
@@ -39,7 +49,7 @@ pub struct FileScanResult {
 pub fn scan(
     paths: Vec<PathBuf>,
     hash_databases: &[impl HashDatabase],
-) -> Result<Vec<FileScanResult>, Box<dyn Error>> {
+) -> Result<Vec<FileScanResult>, ScanError> {
     let mut scan_results = Vec::default();
 
     for path in paths {
@@ -49,7 +59,10 @@ pub fn scan(
             let mut scan_result = Option::default();
 
             for database in hash_databases {
-                if let Some(program_info) = database.get(&file_hash)? {
+                let program_info = database
+                    .get(&file_hash)
+                    .map_err(|error| ScanError::HashDatabaseError(Box::new(error)))?;
+                if let Some(program_info) = program_info {
                     scan_result = Some(FileScanResult {
                         file_path: file_path.clone(),
                         file_hash: file_hash.clone(),
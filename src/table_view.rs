@@ -3,11 +3,13 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use assetinfo::{
-    extractor::Extractor,
+    detection::{detect_all, Detection, DetectionOptions, ProgramDetection},
+    extractor::ExtractorError,
     program::{Program, ProgramInfo, Version},
-    providers::endoflife_date::{CycleId, DateOrBool, EndOfLifeDateClient, ReleaseCycle},
+    providers::{endoflife_date::DateOrBool, Cycle, CycleResolver},
 };
-use std::error::Error;
+use chrono::NaiveDate;
+use log::warn;
 use tabled::{
     settings::{object::Rows, themes::Colorization, Color, Panel, Style},
     Table, Tabled,
@@ -55,6 +57,8 @@ struct ProgramDisplayVersion {
     title: String,
     #[tabled(rename = "Source")]
     source: String,
+    #[tabled(rename = "Path")]
+    location: String,
     #[tabled(rename = "Version")]
     version: String,
     #[tabled(rename = "Release Cycle")]
@@ -65,44 +69,71 @@ struct ProgramDisplayVersion {
     updates_until: String,
     #[tabled(rename = "Security Updates until")]
     security_until: String,
+    #[tabled(rename = "Extended Support until")]
+    extended_until: String,
+    #[tabled(rename = "Latest Release")]
+    latest_release: String,
+}
+
+/// The release cycle of a detected version, or why it has none.
+#[derive(Clone, Copy)]
+enum CycleStatus<'a> {
+    Found(&'a Cycle),
+    /// The program does not reference a lifecycle
+    Untracked,
+    /// No release cycle of the referenced product matches the version
+    NoMatch,
+    /// The lifecycle provider failed
+    ProviderError,
+}
+
+impl<'a> CycleStatus<'a> {
+    fn cycle(self) -> Option<&'a Cycle> {
+        match self {
+            CycleStatus::Found(release_cycle) => Some(release_cycle),
+            CycleStatus::Untracked | CycleStatus::NoMatch | CycleStatus::ProviderError => None,
+        }
+    }
 }
 
 fn version_row(
     program_info: &ProgramInfo,
     version: &Version,
-    release_cycle: &Option<ReleaseCycle>,
+    release_cycle: CycleStatus,
+    releases_behind: usize,
     source: &str,
+    location: &str,
 ) -> ProgramDisplayVersion {
     let today = chrono::Utc::now().date_naive();
 
-    match release_cycle {
-        Some(release_cycle) => {
+    let missing = match release_cycle {
+        CycleStatus::Found(release_cycle) => {
             let (security_until, supported) = match &release_cycle.eol {
                 DateOrBool::Date(eol_date) => {
                     let remaining_time = *eol_date - today;
                     let supported = if remaining_time.num_days() > 0 {
-                        "Yes".to_string()
+                        supported_text(releases_behind)
                     } else {
-                        "No".to_string()
+                        unsupported_text(release_cycle, today)
                     };
 
                     (
-                        format!("{} ({} days)", eol_date, remaining_time.num_days(),),
+                        format!("{} ({} days)", eol_date, remaining_time.num_days()),
                         supported,
                     )
                 }
                 DateOrBool::Bool(eol) => {
                     if *eol {
-                        ("No".to_string(), "No".to_string())
+                        ("No".to_string(), unsupported_text(release_cycle, today))
                     } else {
-                        ("Unknown".to_string(), "Yes".to_string())
+                        ("Unknown".to_string(), supported_text(releases_behind))
                     }
                 }
             };
             let updates_until = match release_cycle.support {
                 Some(DateOrBool::Date(date)) => {
                     let remaining_time = date - today;
-                    format!("{} ({} days)", date, remaining_time.num_days(),)
+                    format!("{} ({} days)", date, remaining_time.num_days())
                 }
                 Some(DateOrBool::Bool(gets_updates)) => {
                     format!("{gets_updates}")
@@ -110,67 +141,127 @@ fn version_row(
                 None => security_until.clone(),
             };
 
-            let cycle = format!("{} ({})", version.cycle, release_cycle.latest);
+            let mut cycle = match release_cycle.latest {
+                Some(ref latest) => format!("{} ({latest})", version.cycle),
+                None => version.cycle.clone(),
+            };
+            if release_cycle.overridden {
+                cycle.push_str(" [policy]");
+            }
+
+            let extended_until = match release_cycle.extended_support {
+                Some(DateOrBool::Date(date)) => {
+                    let remaining_time = date - today;
+                    format!("{} ({} days)", date, remaining_time.num_days())
+                }
+                Some(DateOrBool::Bool(true)) => "Yes".to_string(),
+                Some(DateOrBool::Bool(false)) | None => "No".to_string(),
+            };
+            let latest_release = release_cycle
+                .latest_release_date
+                .map_or_else(|| "Unknown".to_string(), |date| date.to_string());
 
-            ProgramDisplayVersion {
+            return ProgramDisplayVersion {
                 title: program_info.title.clone(),
                 source: source.to_string(),
-                version: version.string.clone(),
+                location: location.to_string(),
+                version: version.to_string(),
                 cycle,
                 supported,
                 updates_until,
                 security_until,
-            }
+                extended_until,
+                latest_release,
+            };
         }
-        None => ProgramDisplayVersion {
-            title: program_info.title.clone(),
-            source: source.to_string(),
-            version: version.string.clone(),
-            cycle: version.cycle.clone(),
-            supported: "Unknown".to_string(),
-            updates_until: "Unknown".to_string(),
-            security_until: "Unknown".to_string(),
-        },
+        CycleStatus::Untracked => "Unknown",
+        CycleStatus::NoMatch => "No matching cycle",
+        CycleStatus::ProviderError => "Error",
+    };
+
+    ProgramDisplayVersion {
+        title: program_info.title.clone(),
+        source: source.to_string(),
+        location: location.to_string(),
+        version: version.to_string(),
+        cycle: version.cycle.clone(),
+        supported: missing.to_string(),
+        updates_until: "Unknown".to_string(),
+        security_until: "Unknown".to_string(),
+        extended_until: "Unknown".to_string(),
+        latest_release: "Unknown".to_string(),
+    }
+}
+
+fn supported_text(releases_behind: usize) -> String {
+    match releases_behind {
+        0 => "Yes".to_string(),
+        1 => "Outdated (1 release behind)".to_string(),
+        _ => format!("Outdated ({releases_behind} releases behind)"),
+    }
+}
+
+fn unsupported_text(release_cycle: &Cycle, today: NaiveDate) -> String {
+    if in_extended_support(release_cycle, today) {
+        "Extended support only".to_string()
+    } else {
+        "No".to_string()
+    }
+}
+
+/// Whether paid extended support is still available for the cycle.
+fn in_extended_support(release_cycle: &Cycle, today: NaiveDate) -> bool {
+    match release_cycle.extended_support {
+        Some(DateOrBool::Date(extended_until)) => extended_until >= today,
+        Some(DateOrBool::Bool(extended)) => extended,
+        None => false,
     }
 }
 
 #[derive(Clone, Copy)]
-enum SupportState {
+pub(crate) enum SupportState {
     Supported,
+    Outdated,
     Security,
+    /// Past the end of life, but covered by paid extended support
+    ExtendedSupport,
     #[allow(dead_code)]
     AlmostEol,
     Unsupported,
     Unknown,
 }
 
-async fn get_release_cycle(
-    program_info: &ProgramInfo,
-    version: &Version,
-    client: &EndOfLifeDateClient,
-) -> Option<ReleaseCycle> {
-    match program_info.endoflife_date_id {
-        Some(ref id) => {
-            match client
-                .get_release_cycle(id, CycleId::String(version.cycle.clone()))
-                .await
-            {
-                Ok(release_cycle) => Some(release_cycle),
-                Err(_error) => None,
-            }
+/// Parses the latest release of the cycle with the scheme of the installed
+/// version and counts how many releases the installation is missing.
+pub(crate) fn get_releases_behind(version: &Version, release_cycle: Option<&Cycle>) -> usize {
+    release_cycle
+        .and_then(|release_cycle| release_cycle.latest.as_ref())
+        .and_then(|latest| Version::parse(latest, &version.cycle, version.scheme).ok())
+        .map_or(0, |latest| version.releases_behind(&latest))
+}
+
+pub(crate) fn get_display_release_cycle(
+    release_cycle: Option<&Cycle>,
+    releases_behind: usize,
+) -> SupportState {
+    let state = get_cycle_support_state(release_cycle);
+
+    match state {
+        SupportState::Supported | SupportState::Security if releases_behind > 0 => {
+            SupportState::Outdated
         }
-        None => None,
+        _ => state,
     }
 }
 
-fn get_display_release_cycle(release_cycle: &Option<ReleaseCycle>) -> SupportState {
+fn get_cycle_support_state(release_cycle: Option<&Cycle>) -> SupportState {
     if let Some(release_cycle) = release_cycle {
         let today = chrono::Utc::now().date_naive();
 
         match release_cycle.eol {
             DateOrBool::Date(eol) => {
                 if eol < today {
-                    SupportState::Unsupported
+                    unsupported_state(release_cycle, today)
                 } else {
                     match release_cycle.support {
                         Some(DateOrBool::Date(supported_until)) => {
@@ -193,7 +284,7 @@ fn get_display_release_cycle(release_cycle: &Option<ReleaseCycle>) -> SupportSta
             }
             DateOrBool::Bool(eol) => {
                 if eol {
-                    SupportState::Unsupported
+                    unsupported_state(release_cycle, today)
                 } else {
                     SupportState::Supported
                 }
@@ -204,49 +295,112 @@ fn get_display_release_cycle(release_cycle: &Option<ReleaseCycle>) -> SupportSta
     }
 }
 
-async fn run_extractor<T: Extractor>(
-    program_info: &ProgramInfo,
-    extractor: &T,
-) -> Option<(ProgramDisplayVersion, SupportState)> {
-    const BASE_URL: &str = "https://endoflife.date/api";
-    let client = EndOfLifeDateClient::new(BASE_URL);
-
-    let version = extractor.version().await;
-    if let Ok(Some(version)) = version {
-        let release_cycle = get_release_cycle(program_info, &version, &client).await;
-        let row = version_row(program_info, &version, &release_cycle, "Binary");
-        return Some((row, get_display_release_cycle(&release_cycle)));
+fn unsupported_state(release_cycle: &Cycle, today: NaiveDate) -> SupportState {
+    if in_extended_support(release_cycle, today) {
+        SupportState::ExtendedSupport
+    } else {
+        SupportState::Unsupported
     }
-    None
 }
 
-pub(crate) async fn list_info_all(programs: Vec<Program>) -> Result<(), Box<dyn Error>> {
-    let mut rows: Vec<(ProgramDisplayVersion, SupportState)> = Vec::default();
+/// Returns `None` if the program is not installed. The second element is
+/// the error of a failed detection.
+fn detection_row(
+    program_detection: ProgramDetection,
+) -> Option<(ProgramDisplayVersion, SupportState, Option<String>)> {
+    let ProgramDetection {
+        program,
+        source,
+        location,
+        detection,
+    } = program_detection;
+    let program_info = &program.info;
+    let location = location.map_or_else(String::default, |path| path.display().to_string());
+    let error = detection.error_text();
+
+    let (version, release_cycle) = match detection {
+        Detection::Found {
+            ref version,
+            release_cycle: Some(ref release_cycle),
+        } => (version, CycleStatus::Found(release_cycle)),
+        Detection::Found {
+            ref version,
+            release_cycle: None,
+        } => {
+            if let Some(reference) = program_info.lifecycle_reference() {
+                warn!(
+                    "No release cycle of {} matches {} version {version} (cycle {})",
+                    reference.product, program_info.title, version.cycle
+                );
+                (version, CycleStatus::NoMatch)
+            } else {
+                (version, CycleStatus::Untracked)
+            }
+        }
+        Detection::ProviderError { ref version, .. } => (version, CycleStatus::ProviderError),
+        Detection::NotInstalled => return None,
+        Detection::ExtractorError(ref extractor_error) => {
+            let supported = match extractor_error {
+                ExtractorError::TimedOut(_) => "Timed out",
+                ExtractorError::PermissionRequired(_) => "Permission required",
+                ExtractorError::NotAllowed(_) => "Not allowed",
+                ExtractorError::NotTrusted(_) => "Not trusted",
+                _ => "Error",
+            };
+            let row = ProgramDisplayVersion {
+                title: program_info.title.clone(),
+                source: source.to_string(),
+                location,
+                version: "Unknown".to_string(),
+                cycle: "Unknown".to_string(),
+                supported: supported.to_string(),
+                updates_until: "Unknown".to_string(),
+                security_until: "Unknown".to_string(),
+                extended_until: "Unknown".to_string(),
+                latest_release: "Unknown".to_string(),
+            };
+            return Some((row, SupportState::Unknown, error));
+        }
+    };
+
+    let releases_behind = get_releases_behind(version, release_cycle.cycle());
+    let row = version_row(
+        program_info,
+        version,
+        release_cycle,
+        releases_behind,
+        source,
+        &location,
+    );
+
+    Some((
+        row,
+        get_display_release_cycle(release_cycle.cycle(), releases_behind),
+        error,
+    ))
+}
+
+/// Returns the number of failed detections.
+pub(crate) async fn list_info_all(
+    programs: &[Program],
+    resolver: &CycleResolver,
+    options: &DetectionOptions,
+) -> usize {
+    let rows: Vec<(ProgramDisplayVersion, SupportState, Option<String>)> =
+        detect_all(programs, resolver, options)
+            .await
+            .into_iter()
+            .filter_map(detection_row)
+            .collect();
 
     let default = Color::FG_BRIGHT_BLACK;
     let supported = Color::FG_GREEN;
+    let outdated = Color::FG_MAGENTA;
     let security = Color::FG_BLUE;
+    let extended = Color::FG_CYAN;
     let warn = Color::FG_YELLOW;
     let unsupported = Color::BOLD | Color::FG_RED;
 
-    for program in programs {
-        // Binary
-        if let Some(binary_extractors) = program.binary {
-            for extractor in binary_extractors {
-                if let Some(row) = run_extractor(&program.info, &extractor).await {
-                    rows.push(row);
-                }
-            }
-        }
-
-        // Docker
-        if let Some(extractor) = program.docker {
-            if let Some(row) = run_extractor(&program.info, &extractor).await {
-                rows.push(row);
-            }
-        }
-    }
-
     let mut table = Table::new(rows.iter().map(|row| row.0.clone()));
     table
         .with(Style::psql())
@@ -257,7 +411,9 @@ pub(crate) async fn list_info_all(programs: Vec<Program>) -> Result<(), Box<dyn
     for (i, state) in support_states.iter().enumerate() {
         let color = match state {
             SupportState::Supported => supported.clone(),
+            SupportState::Outdated => outdated.clone(),
             SupportState::Security => security.clone(),
+            SupportState::ExtendedSupport => extended.clone(),
             SupportState::AlmostEol => warn.clone(),
             SupportState::Unsupported => unsupported.clone(),
             SupportState::Unknown => default.clone(),
@@ -268,5 +424,24 @@ pub(crate) async fn list_info_all(programs: Vec<Program>) -> Result<(), Box<dyn
 
     println!("{table}");
 
-    Ok(())
+    let errors: Vec<_> = rows
+        .iter()
+        .filter_map(|(row, _, error)| error.as_ref().map(|error| (row, error)))
+        .collect();
+
+    if !errors.is_empty() {
+        println!("Errors:");
+        for (row, error) in &errors {
+            if row.location.is_empty() {
+                println!("  {} ({}): {error}", row.title, row.source);
+            } else {
+                println!(
+                    "  {} ({}: {}): {error}",
+                    row.title, row.source, row.location
+                );
+            }
+        }
+    }
+
+    errors.len()
 }
@@ -0,0 +1,318 @@
+// SPDX-FileCopyrightText: 2024 Benedikt Bastin
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use self::{calver::Calver, debian::Debian, pep440::Pep440, rpm::Rpm, semver::Semver};
+
+pub mod calver;
+pub mod debian;
+pub mod pep440;
+pub mod rpm;
+pub mod semver;
+
+/// The versioning scheme a program follows. It decides how a version string
+/// is split into its components and how two versions are compared.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum VersionScheme {
+    #[default]
+    Semver,
+    Debian,
+    Rpm,
+    Calver,
+    Pep440,
+}
+
+pub trait Scheme {
+    fn parse(&self, version: &str) -> Result<ParsedVersion, VersionError>;
+
+    fn scheme_name() -> &'static str;
+}
+
+impl VersionScheme {
+    pub const ALL: [VersionScheme; 5] = [
+        VersionScheme::Semver,
+        VersionScheme::Debian,
+        VersionScheme::Rpm,
+        VersionScheme::Calver,
+        VersionScheme::Pep440,
+    ];
+
+    pub fn parse(self, version: &str) -> Result<ParsedVersion, VersionError> {
+        match self {
+            VersionScheme::Semver => Semver.parse(version),
+            VersionScheme::Debian => Debian.parse(version),
+            VersionScheme::Rpm => Rpm.parse(version),
+            VersionScheme::Calver => Calver.parse(version),
+            VersionScheme::Pep440 => Pep440.parse(version),
+        }
+    }
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            VersionScheme::Semver => Semver::scheme_name(),
+            VersionScheme::Debian => Debian::scheme_name(),
+            VersionScheme::Rpm => Rpm::scheme_name(),
+            VersionScheme::Calver => Calver::scheme_name(),
+            VersionScheme::Pep440 => Pep440::scheme_name(),
+        }
+    }
+}
+
+impl Display for VersionScheme {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.name())
+    }
+}
+
+impl FromStr for VersionScheme {
+    type Err = VersionError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        VersionScheme::ALL
+            .into_iter()
+            .find(|scheme| scheme.name() == name)
+            .ok_or_else(|| VersionError::UnknownScheme(name.to_string()))
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum VersionError {
+    #[error("{version:?} is not a valid {scheme} version")]
+    InvalidVersion {
+        version: String,
+        scheme: &'static str,
+    },
+
+    #[error("Could not parse Version number: {0}")]
+    ParseIntError(#[from] std::num::ParseIntError),
+
+    #[error("Unknown version scheme {0:?}")]
+    UnknownScheme(String),
+
+    #[error("Invalid cycle template {0:?}")]
+    InvalidCycleTemplate(String),
+
+    #[error("Version has no {0} component required by the cycle template")]
+    MissingComponent(&'static str),
+}
+
+impl VersionError {
+    pub(crate) fn invalid<T: Scheme>(version: &str) -> Self {
+        VersionError::InvalidVersion {
+            version: version.to_string(),
+            scheme: T::scheme_name(),
+        }
+    }
+}
+
+/// A version string split up by a [`Scheme`].
+///
+/// `major`, `minor`, `patch` and `extra` are the leading release numbers and
+/// whatever follows them, regardless of the scheme. Only `key` is used for
+/// comparisons.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParsedVersion {
+    pub major: usize,
+    pub minor: Option<usize>,
+    pub patch: Option<usize>,
+    pub extra: Option<String>,
+    pub key: VersionKey,
+}
+
+impl ParsedVersion {
+    /// Takes up to three dot-separated numbers from the start of `release`.
+    pub(crate) fn from_release<T: Scheme>(
+        release: &str,
+        key: VersionKey,
+    ) -> Result<ParsedVersion, VersionError> {
+        let mut numbers = [None; 3];
+        let mut rest = release;
+
+        for (index, number) in numbers.iter_mut().enumerate() {
+            if index > 0 {
+                match rest.strip_prefix('.') {
+                    Some(next_number)
+                        if next_number.starts_with(|next: char| next.is_ascii_digit()) =>
+                    {
+                        rest = next_number;
+                    }
+                    _ => break,
+                }
+            }
+
+            let (digits, tail) = split_digits(rest);
+            if digits.is_empty() {
+                break;
+            }
+            *number = Some(digits.parse::<usize>()?);
+            rest = tail;
+        }
+
+        let major = numbers[0].ok_or_else(|| VersionError::invalid::<T>(release))?;
+
+        Ok(ParsedVersion {
+            major,
+            minor: numbers[1],
+            patch: numbers[2],
+            extra: (!rest.is_empty()).then(|| rest.to_string()),
+            key,
+        })
+    }
+}
+
+/// A scheme-independent sort key. Every scheme maps its versions onto a
+/// sequence of [`KeyPart`]s so that comparing the keys compares the versions.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub struct VersionKey(pub(crate) Vec<KeyPart>);
+
+/// The declaration order is the sort order: a tilde sorts before everything,
+/// even the end of a component, and numbers sort before text.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum KeyPart {
+    Tilde,
+    End,
+    Number(u64),
+    Text(String),
+}
+
+/// Parses a run of digits, saturating instead of failing on overflow.
+pub(crate) fn number(digits: &str) -> u64 {
+    if digits.is_empty() {
+        return 0;
+    }
+    digits.parse::<u64>().unwrap_or(u64::MAX)
+}
+
+/// Splits `text` after its leading ASCII digits.
+pub(crate) fn split_digits(text: &str) -> (&str, &str) {
+    text.split_at(
+        text.find(|next: char| !next.is_ascii_digit())
+            .unwrap_or(text.len()),
+    )
+}
+
+/// Splits all leading dot-separated numbers off `version`.
+pub(crate) fn split_release(version: &str) -> (Vec<u64>, &str) {
+    let mut numbers = Vec::default();
+    let mut rest = version;
+
+    loop {
+        let (digits, tail) = split_digits(rest);
+        if digits.is_empty() {
+            break;
+        }
+        numbers.push(number(digits));
+        rest = tail;
+
+        match rest.strip_prefix('.') {
+            Some(next_number) if next_number.starts_with(|next: char| next.is_ascii_digit()) => {
+                rest = next_number;
+            }
+            _ => break,
+        }
+    }
+
+    (numbers, rest)
+}
+
+/// Appends the alphanumeric runs of `text` to `key`, skipping separators.
+pub(crate) fn push_tokens(key: &mut Vec<KeyPart>, text: &str) {
+    let mut rest = text;
+
+    while let Some(first) = rest.chars().next() {
+        if first == '~' {
+            key.push(KeyPart::Tilde);
+            rest = &rest[1..];
+        } else if first.is_ascii_digit() {
+            let (digits, tail) = split_digits(rest);
+            key.push(KeyPart::Number(number(digits)));
+            rest = tail;
+        } else if first.is_alphanumeric() {
+            let end = rest
+                .find(|next: char| !next.is_alphanumeric() || next.is_ascii_digit())
+                .unwrap_or(rest.len());
+            key.push(KeyPart::Text(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else {
+            rest = &rest[first.len_utf8()..];
+        }
+    }
+}
+
+/// Words that make a suffix directly following the release numbers a
+/// pre-release, e.g. `3.13.0rc2`.
+const PRE_RELEASE_TAGS: [&str; 6] = ["alpha", "beta", "rc", "pre", "preview", "dev"];
+
+/// Whether `suffix` starts with a pre-release tag, or with the short forms
+/// `a` and `b` followed by a number (`3.13.0a1`).
+fn is_pre_release_tag(suffix: &str) -> bool {
+    let end = suffix
+        .find(|next: char| !next.is_alphabetic())
+        .unwrap_or(suffix.len());
+    let (tag, rest) = suffix.split_at(end);
+
+    PRE_RELEASE_TAGS
+        .iter()
+        .any(|pre_release| tag.eq_ignore_ascii_case(pre_release))
+        || (["a", "b"].contains(&tag) && rest.starts_with(|next: char| next.is_ascii_digit()))
+}
+
+/// Appends a suffix following the release numbers to `key`.
+///
+/// A suffix starting with `-`, `~` or a pre-release tag is a pre-release and
+/// sorts before the plain release (`1.0.0-rc1 < 1.0.0`, `1.0~beta < 1.0`,
+/// `3.13.0rc2 < 3.13.0`), any other suffix is a post-release
+/// (`1.0+deb12u1 > 1.0`, `1.1.1w > 1.1.1`).
+pub(crate) fn push_suffix(key: &mut Vec<KeyPart>, suffix: &str) {
+    match suffix.chars().next() {
+        None => {}
+        Some('-' | '~') => {
+            key.push(KeyPart::Tilde);
+            push_tokens(key, &suffix[1..]);
+        }
+        Some(_) if is_pre_release_tag(suffix) => {
+            key.push(KeyPart::Tilde);
+            push_tokens(key, suffix);
+        }
+        Some(_) => push_tokens(key, suffix),
+    }
+    key.push(KeyPart::End);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn release_numbers() {
+        assert_eq!(split_release("1.2.3-rc1"), (vec![1, 2, 3], "-rc1"));
+        assert_eq!(split_release("24.04"), (vec![24, 4], ""));
+        assert_eq!(split_release("1.2."), (vec![1, 2], "."));
+        assert_eq!(split_release("rc1"), (vec![], "rc1"));
+    }
+
+    #[test]
+    fn scheme_names() {
+        let pep440: VersionScheme = serde_json::from_str(r#""pep440""#).unwrap();
+        assert_eq!(pep440, VersionScheme::Pep440);
+        assert_eq!(
+            serde_json::to_string(&VersionScheme::Debian).unwrap(),
+            r#""debian""#
+        );
+
+        for scheme in VersionScheme::ALL {
+            assert_eq!(scheme.to_string().parse::<VersionScheme>().unwrap(), scheme);
+        }
+        assert!("dpkg".parse::<VersionScheme>().is_err());
+    }
+}
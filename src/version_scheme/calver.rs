@@ -0,0 +1,60 @@
+// SPDX-FileCopyrightText: 2024 Benedikt Bastin
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use super::{push_suffix, split_release, KeyPart, ParsedVersion, Scheme, VersionError, VersionKey};
+
+/// Date-based versions such as Ubuntu's `24.04` or `2024.8.1`, with any
+/// number of release numbers. Trailing zeros are insignificant.
+#[derive(Clone, Copy, Debug)]
+pub struct Calver;
+
+impl Scheme for Calver {
+    fn parse(&self, version: &str) -> Result<ParsedVersion, VersionError> {
+        let (mut numbers, suffix) = split_release(version);
+
+        while numbers.last() == Some(&0) {
+            numbers.pop();
+        }
+
+        let mut key: Vec<_> = numbers.into_iter().map(KeyPart::Number).collect();
+        key.push(KeyPart::End);
+        push_suffix(&mut key, suffix);
+
+        ParsedVersion::from_release::<Self>(version, VersionKey(key))
+    }
+
+    fn scheme_name() -> &'static str {
+        "calver"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(version: &str) -> VersionKey {
+        Calver.parse(version).expect("Could not parse version").key
+    }
+
+    #[test]
+    fn ubuntu() {
+        let version = Calver.parse("24.04").expect("Could not parse version");
+
+        assert_eq!(version.major, 24);
+        assert_eq!(version.minor, Some(4));
+        assert_eq!(version.patch, None);
+
+        assert!(key("22.04") < key("24.04"));
+        assert!(key("24.04") < key("24.04.1"));
+        assert!(key("24.04") < key("24.10"));
+        assert_eq!(key("24.04"), key("24.4.0"));
+    }
+
+    #[test]
+    fn many_components() {
+        assert!(key("2024.8.1.3") < key("2024.8.1.10"));
+        assert!(key("2024.8.1-beta") < key("2024.8.1"));
+        assert!(key("2024.8.1-beta") > key("2024.8"));
+    }
+}
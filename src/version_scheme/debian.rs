@@ -0,0 +1,108 @@
+// SPDX-FileCopyrightText: 2024 Benedikt Bastin
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use super::{number, split_digits, KeyPart, ParsedVersion, Scheme, VersionError, VersionKey};
+
+/// Debian package versions `[epoch:]upstream_version[-debian_revision]`,
+/// compared like `dpkg --compare-versions`.
+#[derive(Clone, Copy, Debug)]
+pub struct Debian;
+
+impl Scheme for Debian {
+    fn parse(&self, version: &str) -> Result<ParsedVersion, VersionError> {
+        let (epoch, rest) = match version.split_once(':') {
+            Some((epoch, rest)) if !epoch.is_empty() && split_digits(epoch).1.is_empty() => {
+                (number(epoch), rest)
+            }
+            _ => (0, version),
+        };
+
+        let (upstream, revision) = rest.rsplit_once('-').unwrap_or((rest, ""));
+        if !upstream.starts_with(|next: char| next.is_ascii_digit()) {
+            return Err(VersionError::invalid::<Self>(version));
+        }
+
+        let mut key = vec![KeyPart::Number(epoch)];
+        push_dpkg_key(&mut key, upstream);
+        push_dpkg_key(&mut key, revision);
+
+        ParsedVersion::from_release::<Self>(rest, VersionKey(key))
+    }
+
+    fn scheme_name() -> &'static str {
+        "debian"
+    }
+}
+
+/// dpkg compares alternating non-digit and digit runs. Non-digit runs are
+/// compared character by character with `~` before the end of the run,
+/// letters before everything else, digit runs are compared numerically.
+fn push_dpkg_key(key: &mut Vec<KeyPart>, text: &str) {
+    let mut runs = Vec::default();
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        let end = rest
+            .find(|next: char| next.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (non_digits, tail) = rest.split_at(end);
+        let (digits, tail) = split_digits(tail);
+        runs.push((non_digits, number(digits)));
+        rest = tail;
+    }
+
+    // A missing run equals an empty one, so `1.0` equals `1.0-0`.
+    while runs.last() == Some(&("", 0)) {
+        runs.pop();
+    }
+
+    for (non_digits, digits) in runs {
+        key.extend(non_digits.chars().map(|character| match character {
+            '~' => KeyPart::Tilde,
+            letter if letter.is_ascii_alphabetic() => KeyPart::Number(u64::from(letter)),
+            other => KeyPart::Number(u64::from(other) + 256),
+        }));
+        key.push(KeyPart::End);
+        key.push(KeyPart::Number(digits));
+    }
+    key.push(KeyPart::End);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(version: &str) -> VersionKey {
+        Debian.parse(version).expect("Could not parse version").key
+    }
+
+    #[test]
+    fn components() {
+        let version = Debian
+            .parse("1:2.4.57-2+deb12u1")
+            .expect("Could not parse version");
+
+        assert_eq!(version.major, 2);
+        assert_eq!(version.minor, Some(4));
+        assert_eq!(version.patch, Some(57));
+        assert_eq!(version.extra, Some("-2+deb12u1".to_string()));
+
+        assert!(Debian.parse("deb12u1").is_err());
+    }
+
+    #[test]
+    fn ordering() {
+        assert!(key("2.4.57-2") < key("2.4.57-2+deb12u1"));
+        assert!(key("2.4.57-2+deb12u1") < key("2.4.57-2+deb12u2"));
+        assert!(key("2.4.57-2+deb12u2") < key("2.4.58-1"));
+        assert!(key("2.4.99-1") < key("1:2.4.57-1"));
+        assert!(key("1.0~rc1-1") < key("1.0-1"));
+        assert!(key("1.0~rc1~1") < key("1.0~rc1"));
+        assert!(key("1.0") < key("1.0a"));
+        assert!(key("1.0a") < key("1.0+"));
+        assert!(key("1.0") < key("1.0.0"));
+        assert!(key("1.9") < key("1.10"));
+        assert_eq!(key("1.0"), key("0:1.0-0"));
+    }
+}
@@ -0,0 +1,157 @@
+// SPDX-FileCopyrightText: 2024 Benedikt Bastin
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use super::{number, split_release, KeyPart, ParsedVersion, Scheme, VersionError, VersionKey};
+
+/// Python package versions as specified by PEP 440, including the
+/// alternative spellings allowed by its normalization rules.
+#[derive(Clone, Copy, Debug)]
+pub struct Pep440;
+
+static PEP440_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?xi)^
+        v?
+        (?:(?<epoch>\d+)!)?
+        (?<release>\d+(?:\.\d+)*)
+        (?:[-_.]?(?<pre_label>alpha|a|beta|b|preview|pre|rc|c)[-_.]?(?<pre>\d+)?)?
+        (?:-(?<implicit_post>\d+)|[-_.]?(?<post_label>post|rev|r)[-_.]?(?<post>\d+)?)?
+        (?:[-_.]?(?<dev_label>dev)[-_.]?(?<dev>\d+)?)?
+        (?:\+(?<local>[a-z0-9]+(?:[-_.][a-z0-9]+)*))?
+        $",
+    )
+    .expect("Invalid PEP 440 regex")
+});
+
+// Pre-release phases in ascending order, a final release comes after all.
+const ALPHA: KeyPart = KeyPart::Number(0);
+const BETA: KeyPart = KeyPart::Number(1);
+const RELEASE_CANDIDATE: KeyPart = KeyPart::Number(2);
+const FINAL: KeyPart = KeyPart::Number(3);
+
+impl Scheme for Pep440 {
+    fn parse(&self, version: &str) -> Result<ParsedVersion, VersionError> {
+        let caps = PEP440_REGEX
+            .captures(version)
+            .ok_or_else(|| VersionError::invalid::<Self>(version))?;
+        let group = |name: &str| caps.name(name).map(|group| group.as_str());
+
+        let release = caps.name("release").expect("Release is not optional");
+        let (mut numbers, _) = split_release(release.as_str());
+        while numbers.last() == Some(&0) {
+            numbers.pop();
+        }
+
+        let mut key = vec![KeyPart::Number(number(group("epoch").unwrap_or_default()))];
+        key.extend(numbers.into_iter().map(KeyPart::Number));
+        key.push(KeyPart::End);
+
+        let post = group("implicit_post")
+            .or_else(|| group("post_label").map(|_| group("post").unwrap_or_default()));
+        let dev = group("dev_label").map(|_| group("dev").unwrap_or_default());
+
+        // Development releases sort before any pre-release of the same version.
+        match group("pre_label").map(str::to_lowercase).as_deref() {
+            Some("a" | "alpha") => key.push(ALPHA),
+            Some("b" | "beta") => key.push(BETA),
+            Some(_) => key.push(RELEASE_CANDIDATE),
+            None if post.is_none() && dev.is_some() => key.push(KeyPart::End),
+            None => key.push(FINAL),
+        }
+        key.push(KeyPart::Number(number(group("pre").unwrap_or_default())));
+
+        key.push(post.map_or(KeyPart::End, |post| KeyPart::Number(number(post))));
+        key.push(dev.map_or(KeyPart::Text(String::default()), |dev| {
+            KeyPart::Number(number(dev))
+        }));
+
+        if let Some(local) = group("local") {
+            for segment in local.split(['-', '_', '.']) {
+                if segment.starts_with(|next: char| next.is_ascii_digit())
+                    && segment.chars().all(|next| next.is_ascii_digit())
+                {
+                    key.extend([KeyPart::Number(1), KeyPart::Number(number(segment))]);
+                } else {
+                    key.extend([KeyPart::Number(0), KeyPart::Text(segment.to_lowercase())]);
+                }
+            }
+        }
+        key.push(KeyPart::End);
+
+        ParsedVersion::from_release::<Self>(&version[release.start()..], VersionKey(key))
+    }
+
+    fn scheme_name() -> &'static str {
+        "pep440"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(version: &str) -> VersionKey {
+        Pep440.parse(version).expect("Could not parse version").key
+    }
+
+    #[test]
+    fn components() {
+        let version = Pep440.parse("3.13.0rc2").expect("Could not parse version");
+
+        assert_eq!(version.major, 3);
+        assert_eq!(version.minor, Some(13));
+        assert_eq!(version.patch, Some(0));
+        assert_eq!(version.extra, Some("rc2".to_string()));
+
+        let epoch = Pep440.parse("1!2.0").expect("Could not parse version");
+        assert_eq!(epoch.major, 2);
+
+        assert!(Pep440.parse("3.13.0-foo").is_err());
+    }
+
+    #[test]
+    fn ordering() {
+        let ordered = [
+            "1.0.dev456",
+            "1.0a1",
+            "1.0a2.dev456",
+            "1.0a12.dev456",
+            "1.0a12",
+            "1.0b1.dev456",
+            "1.0b2",
+            "1.0b2.post345.dev456",
+            "1.0b2.post345",
+            "1.0rc1.dev456",
+            "1.0rc1",
+            "1.0",
+            "1.0+abc.5",
+            "1.0+abc.7",
+            "1.0+5",
+            "1.0.post456.dev34",
+            "1.0.post456",
+            "1.0.15",
+            "1.1.dev1",
+            "1!0.1",
+        ];
+
+        for pair in ordered.windows(2) {
+            assert!(key(pair[0]) < key(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn normalization() {
+        assert_eq!(key("1.0"), key("1.0.0"));
+        assert_eq!(key("1.0a1"), key("1.0-alpha.1"));
+        assert_eq!(key("1.0rc1"), key("1.0c1"));
+        assert_eq!(key("1.0.post1"), key("1.0-1"));
+        assert_eq!(key("1.0.post0"), key("1.0.post"));
+        assert_eq!(key("1.0.dev0"), key("1.0-DEV"));
+        assert_eq!(key("v1.0"), key("1.0"));
+    }
+}
@@ -0,0 +1,103 @@
+// SPDX-FileCopyrightText: 2024 Benedikt Bastin
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use super::{number, split_digits, KeyPart, ParsedVersion, Scheme, VersionError, VersionKey};
+
+/// RPM package versions `[epoch:]version[-release]`, compared like
+/// `rpmvercmp`.
+#[derive(Clone, Copy, Debug)]
+pub struct Rpm;
+
+impl Scheme for Rpm {
+    fn parse(&self, version: &str) -> Result<ParsedVersion, VersionError> {
+        let (epoch, rest) = match version.split_once(':') {
+            Some((epoch, rest)) if !epoch.is_empty() && split_digits(epoch).1.is_empty() => {
+                (number(epoch), rest)
+            }
+            _ => (0, version),
+        };
+
+        let (upstream, release) = rest.rsplit_once('-').unwrap_or((rest, ""));
+
+        let mut key = vec![KeyPart::Number(epoch)];
+        push_rpm_key(&mut key, upstream);
+        push_rpm_key(&mut key, release);
+
+        ParsedVersion::from_release::<Self>(rest, VersionKey(key))
+    }
+
+    fn scheme_name() -> &'static str {
+        "rpm"
+    }
+}
+
+// Segment markers: `^` sorts after the end of the version but before any
+// further segment, letters sort before numbers.
+const CARET: KeyPart = KeyPart::Number(0);
+const ALPHA: KeyPart = KeyPart::Number(1);
+const NUMERIC: KeyPart = KeyPart::Number(2);
+
+/// rpm compares alphanumeric segments and ignores all other separators
+/// except `~` (sorts before everything) and `^`.
+fn push_rpm_key(key: &mut Vec<KeyPart>, text: &str) {
+    let mut rest = text;
+
+    while let Some(first) = rest.chars().next() {
+        if first == '~' {
+            key.push(KeyPart::Tilde);
+            rest = &rest[1..];
+        } else if first == '^' {
+            key.push(CARET);
+            rest = &rest[1..];
+        } else if first.is_ascii_digit() {
+            let (digits, tail) = split_digits(rest);
+            key.extend([NUMERIC, KeyPart::Number(number(digits))]);
+            rest = tail;
+        } else if first.is_ascii_alphabetic() {
+            let end = rest
+                .find(|next: char| !next.is_ascii_alphabetic())
+                .unwrap_or(rest.len());
+            key.extend([ALPHA, KeyPart::Text(rest[..end].to_string())]);
+            rest = &rest[end..];
+        } else {
+            rest = &rest[first.len_utf8()..];
+        }
+    }
+    key.push(KeyPart::End);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(version: &str) -> VersionKey {
+        Rpm.parse(version).expect("Could not parse version").key
+    }
+
+    #[test]
+    fn components() {
+        let version = Rpm
+            .parse("5.14.0-362.el9")
+            .expect("Could not parse version");
+
+        assert_eq!(version.major, 5);
+        assert_eq!(version.minor, Some(14));
+        assert_eq!(version.patch, Some(0));
+        assert_eq!(version.extra, Some("-362.el9".to_string()));
+    }
+
+    #[test]
+    fn ordering() {
+        assert!(key("5.14.0-362.el9") < key("5.14.0-362.8.1.el9_3"));
+        assert!(key("5.14.0-362.el9") < key("5.14.0-427.el9"));
+        assert!(key("5.14.0-427.el9") < key("1:5.14.0-1.el9"));
+        assert!(key("1.0~rc1") < key("1.0"));
+        assert!(key("1.0") < key("1.0^git1"));
+        assert!(key("1.0^git1") < key("1.0.1"));
+        assert!(key("1.0a") < key("1.0.1"));
+        assert!(key("1.0") < key("1.0a"));
+        assert_eq!(key("1.0"), key("1_0"));
+        assert_eq!(key("1.01"), key("1.1"));
+    }
+}
@@ -0,0 +1,77 @@
+// SPDX-FileCopyrightText: 2024 Benedikt Bastin
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use super::{push_suffix, KeyPart, ParsedVersion, Scheme, VersionError, VersionKey};
+
+/// `MAJOR[.MINOR[.PATCH]][SUFFIX]`, missing numbers count as `0`.
+///
+/// This is lenient enough to be the default for programs that do not declare
+/// a scheme.
+#[derive(Clone, Copy, Debug)]
+pub struct Semver;
+
+impl Scheme for Semver {
+    fn parse(&self, version: &str) -> Result<ParsedVersion, VersionError> {
+        let version = version.strip_prefix('v').unwrap_or(version);
+
+        let mut parsed = ParsedVersion::from_release::<Self>(version, VersionKey::default())?;
+
+        let mut key = [Some(parsed.major), parsed.minor, parsed.patch]
+            .iter()
+            .map(|number| KeyPart::Number(number.unwrap_or(0) as u64))
+            .collect();
+        push_suffix(&mut key, parsed.extra.as_deref().unwrap_or_default());
+
+        parsed.key = VersionKey(key);
+        Ok(parsed)
+    }
+
+    fn scheme_name() -> &'static str {
+        "semver"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(version: &str) -> VersionKey {
+        Semver.parse(version).expect("Could not parse version").key
+    }
+
+    #[test]
+    fn components() {
+        let version = Semver
+            .parse("v1.18.0-rc1+build5")
+            .expect("Could not parse version");
+
+        assert_eq!(version.major, 1);
+        assert_eq!(version.minor, Some(18));
+        assert_eq!(version.patch, Some(0));
+        assert_eq!(version.extra, Some("-rc1+build5".to_string()));
+
+        assert!(Semver.parse("rc1").is_err());
+    }
+
+    #[test]
+    fn ordering() {
+        assert_eq!(key("1.0"), key("1.0.0"));
+        assert!(key("1.0.0-rc1") < key("1.0.0"));
+        assert!(key("1.0.0-alpha") < key("1.0.0-alpha.1"));
+        assert!(key("1.0.0-1") < key("1.0.0-alpha"));
+        assert!(key("1.0.0") < key("1.0.0.1"));
+        assert!(key("1.0.0.1") < key("1.0.1"));
+    }
+
+    #[test]
+    fn letter_suffixes() {
+        assert!(key("3.13.0rc2") < key("3.13.0"));
+        assert!(key("3.13.0a1") < key("3.13.0b1"));
+        assert!(key("3.13.0b1") < key("3.13.0"));
+
+        assert!(key("1.1.1") < key("1.1.1a"));
+        assert!(key("1.1.1a") < key("1.1.1w"));
+        assert!(key("1.1.1w") < key("1.1.2"));
+    }
+}
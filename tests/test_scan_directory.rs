@@ -2,12 +2,13 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use std::{collections::HashMap, error::Error, fs::File, io::Write};
+use std::{collections::HashMap, convert::Infallible, fs::File, io, io::Write};
 
 use assetinfo::{
     hash_database::{HashDatabase, VersionedProgramInfo},
     program::Version,
-    scan::scan,
+    scan::{scan, ScanError},
+    version_scheme::VersionScheme,
 };
 use tempfile::TempDir;
 
@@ -16,11 +17,24 @@ pub struct MockHashDatabase {
 }
 
 impl HashDatabase for MockHashDatabase {
-    fn get(&self, hash: &str) -> Result<Option<VersionedProgramInfo>, Box<dyn Error>> {
+    type Error = Infallible;
+
+    fn get(&self, hash: &str) -> Result<Option<VersionedProgramInfo>, Infallible> {
         Ok(self.hashes.get(hash).cloned())
     }
 }
 
+#[derive(Clone, Copy)]
+pub struct FailingHashDatabase;
+
+impl HashDatabase for FailingHashDatabase {
+    type Error = io::Error;
+
+    fn get(&self, _hash: &str) -> Result<Option<VersionedProgramInfo>, io::Error> {
+        Err(io::Error::other("database unavailable"))
+    }
+}
+
 #[test]
 fn scan_directory() {
     let tmp_dir = TempDir::new().expect("Could not create tmpdir");
@@ -42,14 +56,7 @@ fn scan_directory() {
         VersionedProgramInfo {
             id: "com.example.txt.test".to_owned(),
             title: "test.txt".to_owned(),
-            version: Version {
-                string: "1.0.0".to_owned(),
-                cycle: "1.0".to_owned(),
-                major: 1,
-                minor: Some(0),
-                patch: Some(0),
-                extra: None,
-            },
+            version: Version::parse("1.0.0", "1.0", VersionScheme::Semver).unwrap(),
         },
     );
 
@@ -80,3 +87,13 @@ fn scan_directory() {
     assert!(supported_result.program_info.is_some());
     assert!(unsupported_result.program_info.is_none());
 }
+
+#[test]
+fn hash_database_errors() {
+    let tmp_dir = TempDir::new().expect("Could not create tmpdir");
+    File::create(tmp_dir.path().join("test.txt")).expect("Could not create file");
+
+    let result = scan(vec![tmp_dir.path().to_path_buf()], &[FailingHashDatabase]);
+
+    assert!(matches!(result, Err(ScanError::HashDatabaseError(_))));
+}